@@ -1,13 +1,45 @@
 use structopt::clap;
 use structopt::StructOpt;
 
+use ipnet::IpNet;
+
+use crate::migrate::{Endpoint, LineMap};
+use crate::output::{Format, FORMATS};
+use crate::provider::interface::{RecordSpec, RecordStatus};
+use crate::zone::{ZoneFormat, ZONE_FORMATS};
+
 #[derive(Debug, StructOpt, PartialEq)]
 #[structopt(name = "cao", about = "IP Update")]
-pub enum Args {
+pub struct Args {
+  /// Configuration file.
+  /// Defaults to /etc/cao/cao.toml for `plan` and `apply`.
+  #[structopt(long, global = true)]
+  pub config: Option<String>,
+  /// Output format: plain, table, json or csv.
+  /// In JSON, add, modify and delete also give the `record` listed again
+  /// from the provider after the change, or before the deletion.
+  #[structopt(
+    long,
+    global = true,
+    default_value = "plain",
+    possible_values = FORMATS
+  )]
+  pub output: Format,
+  /// Log the changes instead of making them.
+  /// Records are still listed from the provider.
+  #[structopt(long, global = true)]
+  pub dry_run: bool,
+  /// sub command
+  #[structopt(subcommand)]
+  pub cmd: Cmds,
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub enum Cmds {
   #[structopt(about = "Record operation")]
   Record {
     /// DNS API Provider.
-    /// Only DNSPOD now.
+    /// dnspod, cloudflare, alidns or rfc2136.
     #[structopt(short, long)]
     provider: String,
     /// Token in file.
@@ -21,6 +53,116 @@ pub enum Args {
     #[structopt(subcommand)]
     cmd: RecordCmds,
   },
+  #[structopt(about = "Zone operation")]
+  Zone {
+    /// DNS API Provider.
+    /// dnspod, cloudflare, alidns or rfc2136.
+    #[structopt(short, long)]
+    provider: String,
+    /// Token in file.
+    /// The file only contains the token.
+    #[structopt(short, long)]
+    key: String,
+    /// Domain
+    #[structopt(short, long)]
+    domain: String,
+    /// sub command
+    #[structopt(subcommand)]
+    cmd: ZoneCmds,
+  },
+  #[structopt(
+    about = "Keep records updated with the current address",
+    long_about = "Keep records updated with the current address.\n\
+                  With --config every record of the configuration file \
+                  is kept updated, otherwise the record is given by the \
+                  options."
+  )]
+  Daemon {
+    /// DNS API Provider.
+    /// dnspod, cloudflare, alidns or rfc2136.
+    #[structopt(short, long)]
+    provider: Option<String>,
+    /// Token in file.
+    /// The file only contains the token.
+    /// The file is read again on SIGHUP.
+    #[structopt(short, long)]
+    key: Option<String>,
+    /// Domain
+    #[structopt(short, long)]
+    domain: Option<String>,
+    /// Subdomain
+    #[structopt(short, long = "sub")]
+    sub_domain: Option<String>,
+    /// Record type
+    #[structopt(short = "t", long = "type")]
+    record_type: Option<String>,
+    /// Record line
+    #[structopt(short = "l", long = "line")]
+    record_line: Option<String>,
+    /// Value
+    #[structopt(short, long)]
+    value: Option<String>,
+    /// Get value from interface, as `NAME?KEY=VALUE&...`.
+    /// Keys are family, scope, include, exclude, prefer, index and suffix.
+    #[structopt(long = "if")]
+    interface: Option<String>,
+    /// Get value from a public address source.
+    /// `http[:URL,...]`, `stun[:HOST[:PORT]]`,
+    /// `dns[:opendns|google][@RESOLVER]` or `upnp[:HOST[:PORT]]`.
+    #[structopt(long)]
+    ip_source: Option<String>,
+    /// Seconds between two polls of the address
+    #[structopt(long, default_value = "300")]
+    interval: u64,
+  },
+  #[structopt(
+    about = "Show the changes bringing the records to the configuration",
+    long_about = "Show the changes bringing the records to the \
+                  configuration.\n\
+                  Records with the subdomain, type and line of a record \
+                  of the configuration file are managed, the others are \
+                  unmanaged and only deleted with --prune."
+  )]
+  Plan {
+    /// Delete the unmanaged records too
+    #[structopt(long)]
+    prune: bool,
+  },
+  #[structopt(about = "Bring the records to the configuration, see `plan`")]
+  Apply {
+    /// Delete the unmanaged records too
+    #[structopt(long)]
+    prune: bool,
+  },
+  #[structopt(
+    about = "Copy every record of a domain to another provider",
+    long_about = "Copy every record of a domain to another provider.\n\
+                  Records whose line or type has no counterpart on the \
+                  target are reported and skipped, SOA records and NS \
+                  records of the apex too. The target is listed again \
+                  afterwards and the differences left are reported."
+  )]
+  Migrate {
+    /// Source as PROVIDER:DOMAIN, e.g. dnspod:example.com
+    #[structopt(long)]
+    from: Endpoint,
+    /// Token file of the source
+    #[structopt(long)]
+    from_key: String,
+    /// Target as PROVIDER:DOMAIN, e.g. cloudflare:example.com
+    #[structopt(long)]
+    to: Endpoint,
+    /// Token file of the target
+    #[structopt(long)]
+    to_key: String,
+    /// Line of the target for a line of the source, as FROM=TO.
+    /// May be repeated.
+    #[structopt(long = "line-map", number_of_values = 1)]
+    line_maps: Vec<LineMap>,
+    /// Delete the records of the target which are not in the source
+    #[structopt(long)]
+    prune: bool,
+  },
   #[structopt(about = "List interfaces")]
   Interface {
     #[structopt(short, long)]
@@ -28,6 +170,38 @@ pub enum Args {
   },
 }
 
+/// Optional fields of the record to add, modify or sync.
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RecordSpecArgs {
+  /// TTL in seconds
+  #[structopt(long)]
+  pub ttl: Option<u32>,
+  /// Priority of MX records
+  #[structopt(long)]
+  pub mx: Option<u16>,
+  /// Weight
+  #[structopt(long)]
+  pub weight: Option<u32>,
+  /// Status: enabled or disabled
+  #[structopt(long)]
+  pub status: Option<RecordStatus>,
+  /// Remark
+  #[structopt(long)]
+  pub remark: Option<String>,
+}
+
+impl From<RecordSpecArgs> for RecordSpec {
+  fn from(args: RecordSpecArgs) -> Self {
+    RecordSpec {
+      ttl: args.ttl,
+      mx: args.mx,
+      weight: args.weight,
+      status: args.status,
+      remark: args.remark,
+    }
+  }
+}
+
 #[derive(Debug, StructOpt, PartialEq)]
 pub enum RecordCmds {
   #[structopt(about = "Add a record")]
@@ -44,9 +218,17 @@ pub enum RecordCmds {
     /// Value
     #[structopt(short, long)]
     value: Option<String>,
-    /// Get value from interface
+    /// Get value from interface, as `NAME?KEY=VALUE&...`.
+    /// Keys are family, scope, include, exclude, prefer, index and suffix.
     #[structopt(long = "if")]
     interface: Option<String>,
+    /// Get value from a public address source.
+    /// `http[:URL,...]`, `stun[:HOST[:PORT]]`,
+    /// `dns[:opendns|google][@RESOLVER]` or `upnp[:HOST[:PORT]]`.
+    #[structopt(long)]
+    ip_source: Option<String>,
+    #[structopt(flatten)]
+    spec: RecordSpecArgs,
   },
   #[structopt(about = "List records")]
   List {
@@ -59,12 +241,41 @@ pub enum RecordCmds {
     /// Subdomain
     #[structopt(short, long)]
     sub_domain: Option<String>,
+    /// Record type
+    #[structopt(short = "t", long = "type")]
+    record_type: Option<String>,
+    /// Record line
+    #[structopt(long = "line")]
+    record_line: Option<String>,
+    /// Value, matched exactly
+    #[structopt(long, conflicts_with_all = &["value-contains", "value-in"])]
+    value: Option<String>,
+    /// Text found in the value
+    #[structopt(long, conflicts_with = "value-in")]
+    value_contains: Option<String>,
+    /// Network holding the address of the value, e.g. 10.0.0.0/8
+    #[structopt(long)]
+    value_in: Option<IpNet>,
+    /// Status: enabled or disabled
+    #[structopt(long)]
+    status: Option<RecordStatus>,
+    /// Text found in the subdomain or the value
+    #[structopt(long)]
+    keyword: Option<String>,
+    /// List every record, page by page
+    #[structopt(short, long, conflicts_with_all = &["offset", "length"])]
+    all: bool,
   },
-  #[structopt(about = "Modify a record")]
+  #[structopt(
+    about = "Modify a record",
+    long_about = "Modify a record.\n\
+                  Without --id the record is the one with the subdomain, \
+                  type and line."
+  )]
   Modify {
     /// Record ID
-    #[structopt(short = "i", long = "id")]
-    record_id: u64,
+    #[structopt(short = "i", long = "id", required_unless = "sub-domain")]
+    record_id: Option<String>,
     /// Subdomain
     #[structopt(short, long = "sub")]
     sub_domain: Option<String>,
@@ -77,22 +288,137 @@ pub enum RecordCmds {
     /// Value
     #[structopt(short, long)]
     value: Option<String>,
-    /// Get value from interface
+    /// Get value from interface, as `NAME?KEY=VALUE&...`.
+    /// Keys are family, scope, include, exclude, prefer, index and suffix.
     #[structopt(long = "if")]
     interface: Option<String>,
+    /// Get value from a public address source.
+    /// `http[:URL,...]`, `stun[:HOST[:PORT]]`,
+    /// `dns[:opendns|google][@RESOLVER]` or `upnp[:HOST[:PORT]]`.
+    #[structopt(long)]
+    ip_source: Option<String>,
+    #[structopt(flatten)]
+    spec: RecordSpecArgs,
+    /// Modify every record found without --id
+    #[structopt(short, long, conflicts_with = "record-id")]
+    all: bool,
   },
-  #[structopt(about = "Delete a record")]
+  #[structopt(
+    about = "Delete a record",
+    long_about = "Delete a record.\n\
+                  Without --id the record is the one with the subdomain, \
+                  and the type and line when given."
+  )]
   Delete {
+    /// Record ID
+    #[structopt(short = "i", long = "id", required_unless = "sub-domain")]
+    record_id: Option<String>,
+    /// Subdomain
+    #[structopt(short, long = "sub", conflicts_with = "record-id")]
+    sub_domain: Option<String>,
+    /// Record type
+    #[structopt(short = "t", long = "type", requires = "sub-domain")]
+    record_type: Option<String>,
+    /// Record line
+    #[structopt(short = "l", long = "line", requires = "sub-domain")]
+    record_line: Option<String>,
+    /// Delete every record found without --id
+    #[structopt(short, long, requires = "sub-domain")]
+    all: bool,
+  },
+  #[structopt(about = "Enable a record")]
+  Enable {
+    /// Record ID
+    #[structopt(short = "i", long = "id")]
+    record_id: String,
+  },
+  #[structopt(about = "Disable a record, keeping it and its id")]
+  Disable {
     /// Record ID
     #[structopt(short = "i", long = "id")]
-    record_id: u64,
+    record_id: String,
+  },
+  #[structopt(
+    about = "Create or update a record to hold the value",
+    long_about = "Create or update a record to hold the value.\n\
+                  Exits with 0 when unchanged, 10 when created \
+                  and 11 when updated."
+  )]
+  Sync {
+    /// Subdomain
+    #[structopt(short, long = "sub")]
+    sub_domain: String,
+    /// Record type
+    #[structopt(short = "t", long = "type")]
+    record_type: String,
+    /// Record line
+    #[structopt(short = "l", long = "line")]
+    record_line: String,
+    /// Value
+    #[structopt(short, long)]
+    value: Option<String>,
+    /// Get value from interface, as `NAME?KEY=VALUE&...`.
+    /// Keys are family, scope, include, exclude, prefer, index and suffix.
+    #[structopt(long = "if")]
+    interface: Option<String>,
+    /// Get value from a public address source.
+    /// `http[:URL,...]`, `stun[:HOST[:PORT]]`,
+    /// `dns[:opendns|google][@RESOLVER]` or `upnp[:HOST[:PORT]]`.
+    #[structopt(long)]
+    ip_source: Option<String>,
+    #[structopt(flatten)]
+    spec: RecordSpecArgs,
+  },
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub enum ZoneCmds {
+  #[structopt(about = "Print every record as a zone file")]
+  Export {
+    /// File format: bind, json, yaml or csv.
+    #[structopt(
+      short,
+      long,
+      default_value = "bind",
+      possible_values = ZONE_FORMATS
+    )]
+    format: ZoneFormat,
+  },
+  #[structopt(
+    about = "Add the records of a zone file",
+    long_about = "Add the records of a zone file.\n\
+                  SOA records and NS records of the apex are skipped."
+  )]
+  Import {
+    /// Zone file
+    file: String,
+    /// File format: bind, json, yaml or csv.
+    /// Defaults to the one of the file extension, else bind.
+    #[structopt(short, long, possible_values = ZONE_FORMATS)]
+    format: Option<ZoneFormat>,
+    /// Record line of the added records.
+    /// Defaults to the line of each record in the file, else 0.
+    #[structopt(short = "l", long = "line")]
+    record_line: Option<String>,
   },
 }
 
 fn missing_if_or_value() -> clap::Error {
   clap::Error {
     message: String::from(
-      "error: Missing one of following required arguments:\n --if or --value",
+      "error: Missing one of following required arguments:\n \
+       --if, --ip-source or --value",
+    ),
+    kind: clap::ErrorKind::MissingRequiredArgument,
+    info: None,
+  }
+}
+
+fn missing_daemon_record() -> clap::Error {
+  clap::Error {
+    message: String::from(
+      "error: Missing --config or all of following required arguments:\n \
+       --provider, --key, --domain, --sub, --type and --line",
     ),
     kind: clap::ErrorKind::MissingRequiredArgument,
     info: None,
@@ -103,26 +429,64 @@ impl Args {
   pub fn get_args() -> Result<Self, clap::Error> {
     let args = Self::from_args_safe()?;
 
-    if let Args::Record { cmd, .. } = &args {
-      match cmd {
-        RecordCmds::Add {
-          value, interface, ..
-        } => {
-          if value.is_none() && interface.is_none() {
-            return Err(missing_if_or_value());
-          }
+    if let Cmds::Daemon {
+      provider,
+      key,
+      domain,
+      sub_domain,
+      record_type,
+      record_line,
+      value,
+      interface,
+      ip_source,
+      ..
+    } = &args.cmd
+    {
+      if args.config.is_none() {
+        if provider.is_none()
+          || key.is_none()
+          || domain.is_none()
+          || sub_domain.is_none()
+          || record_type.is_none()
+          || record_line.is_none()
+        {
+          return Err(missing_daemon_record());
         }
-        RecordCmds::Modify {
-          value, interface, ..
-        } => {
-          if value.is_none() && interface.is_none() {
-            return Err(missing_if_or_value());
-          }
+        if value.is_none() && interface.is_none() && ip_source.is_none() {
+          return Err(missing_if_or_value());
         }
-        _ => {}
       }
     }
 
+    let no_value = match &args.cmd {
+      Cmds::Record {
+        cmd:
+          RecordCmds::Add {
+            value,
+            interface,
+            ip_source,
+            ..
+          }
+          | RecordCmds::Modify {
+            value,
+            interface,
+            ip_source,
+            ..
+          }
+          | RecordCmds::Sync {
+            value,
+            interface,
+            ip_source,
+            ..
+          },
+        ..
+      } => value.is_none() && interface.is_none() && ip_source.is_none(),
+      _ => false,
+    };
+    if no_value {
+      return Err(missing_if_or_value());
+    }
+
     Ok(args)
   }
 }
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::fetch_key;
+use crate::provider::build_dns_provider;
+use crate::provider::interface::{DnsProvider, RecordSpec};
+
+pub const DEFAULT_CONFIG: &str = "/etc/cao/cao.toml";
+
+/// Content of the configuration file.
+///
+/// ```toml
+/// [providers.home]
+/// kind = "dnspod"
+/// key = "/etc/cao/dnspod.key"
+///
+/// [[domains]]
+/// name = "example.com"
+/// provider = "home"
+///
+/// [[domains.records]]
+/// sub = "www"
+/// type = "A"
+/// line = "默认"
+/// interface = "eth0,4"
+/// ```
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+  #[serde(default)]
+  pub providers: BTreeMap<String, ProviderConfig>,
+  #[serde(default)]
+  pub domains: Vec<DomainConfig>,
+}
+
+/// A provider account.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderConfig {
+  /// Provider kind as given to `--provider`.
+  /// Defaults to the name of the entry.
+  pub kind: Option<String>,
+  /// File containing the token.
+  pub key: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DomainConfig {
+  pub name: String,
+  /// Name of the entry in `providers`.
+  pub provider: String,
+  #[serde(default)]
+  pub records: Vec<RecordConfig>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+  #[serde(rename = "sub")]
+  pub sub_domain: String,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  #[serde(rename = "line")]
+  pub record_line: String,
+  pub ttl: Option<u32>,
+  pub value: Option<String>,
+  pub interface: Option<String>,
+  /// Public address source, see `--ip-source`.
+  pub ip_source: Option<String>,
+}
+
+impl Config {
+  pub fn load(file_name: &str) -> Result<Self> {
+    let content = std::fs::read_to_string(file_name)?;
+    let config: Config = toml::from_str(&content)?;
+    config.validate()?;
+    Ok(config)
+  }
+
+  fn validate(&self) -> Result<()> {
+    for domain in &self.domains {
+      if !self.providers.contains_key(&domain.provider) {
+        return Err(Error::Reason(format!(
+          "Unknown provider {} of domain {}",
+          domain.provider, domain.name
+        )));
+      }
+      for record in &domain.records {
+        if record.value.is_none()
+          && record.interface.is_none()
+          && record.ip_source.is_none()
+        {
+          return Err(Error::Reason(format!(
+            "Missing value, interface or ip_source of record {}.{}",
+            record.sub_domain, domain.name
+          )));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Build the provider of every domain with its records.
+  pub fn build(
+    &self,
+    dry_run: bool,
+  ) -> Result<Vec<(Rc<dyn DnsProvider>, &DomainConfig)>> {
+    self
+      .domains
+      .iter()
+      .map(|domain| {
+        let provider = &self.providers[&domain.provider];
+        Ok((
+          provider.build(&domain.provider, &domain.name, dry_run)?,
+          domain,
+        ))
+      })
+      .collect()
+  }
+}
+
+impl RecordConfig {
+  /// Optional fields of the record, only the TTL can be configured.
+  pub fn spec(&self) -> RecordSpec {
+    RecordSpec {
+      ttl: self.ttl,
+      ..RecordSpec::default()
+    }
+  }
+}
+
+impl ProviderConfig {
+  pub fn build(
+    &self,
+    name: &str,
+    domain: &str,
+    dry_run: bool,
+  ) -> Result<Rc<dyn DnsProvider>> {
+    let kind = self.kind.as_deref().unwrap_or(name);
+    let key = fetch_key(self.key.clone())?;
+    Ok(Rc::from(build_dns_provider(
+      kind,
+      key,
+      String::from(domain),
+      dry_run,
+    )?))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_config_parse() {
+    let config: Config = toml::from_str(
+      r#"
+      [providers.dnspod]
+      key = "/etc/cao/dnspod.key"
+
+      [[domains]]
+      name = "example.com"
+      provider = "dnspod"
+
+      [[domains.records]]
+      sub = "www"
+      type = "A"
+      line = "默认"
+      ttl = 600
+      interface = "eth0,4"
+      "#,
+    )
+    .unwrap();
+    config.validate().unwrap();
+
+    assert_eq!(config.providers["dnspod"].kind, None);
+    assert_eq!(
+      config.domains[0].records[0],
+      RecordConfig {
+        sub_domain: String::from("www"),
+        record_type: String::from("A"),
+        record_line: String::from("默认"),
+        ttl: Some(600),
+        value: None,
+        interface: Some(String::from("eth0,4")),
+        ip_source: None,
+      }
+    );
+
+    let config: Config = toml::from_str(
+      r#"
+      [[domains]]
+      name = "example.com"
+      provider = "cloudflare"
+      "#,
+    )
+    .unwrap();
+    assert!(config.validate().is_err());
+  }
+}
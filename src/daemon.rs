@@ -0,0 +1,219 @@
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::interface::{interface_or_value, selector_interface};
+use crate::provider::interface::{DnsProvider, RecordSpec};
+use crate::sync::sync_record;
+use crate::watch::Watcher;
+
+/// Delay before the first retry of a failed update.
+const BACKOFF_MIN: Duration = Duration::from_secs(5);
+/// Upper bound of the retry delay.
+const BACKOFF_MAX: Duration = Duration::from_secs(30 * 60);
+/// Granularity used to check the signal flags while sleeping.
+const TICK: Duration = Duration::from_millis(200);
+/// Delay between an address change and the update, so that the changes of
+/// a burst, e.g. a DHCP renewal, lead to a single update.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// A record kept up to date by the daemon.
+pub struct Target {
+  pub provider: Rc<dyn DnsProvider>,
+  pub sub_domain: String,
+  pub record_type: String,
+  pub record_line: String,
+  pub value: Option<String>,
+  pub interface: Option<String>,
+  pub ip_source: Option<String>,
+  pub spec: RecordSpec,
+}
+
+/// Per target state between two polls.
+struct State {
+  target: Target,
+  /// Last value published to the provider.
+  published: Option<String>,
+  failures: u32,
+  next: Instant,
+}
+
+impl State {
+  fn new(target: Target) -> Self {
+    State {
+      target,
+      published: None,
+      failures: 0,
+      next: Instant::now(),
+    }
+  }
+
+  /// Whether the value comes from one of the interfaces, an empty name
+  /// meaning any interface.
+  fn watches(&self, names: &[String]) -> bool {
+    let target = &self.target;
+    if target.value.is_some() || target.ip_source.is_some() {
+      return false;
+    }
+    match target.interface.as_deref().map(selector_interface) {
+      Some(Ok(interface)) => names
+        .iter()
+        .any(|name| name.is_empty() || *name == interface),
+      _ => false,
+    }
+  }
+
+  fn poll(&mut self) -> Result<()> {
+    let target = &self.target;
+    let value = interface_or_value(
+      target.interface.clone(),
+      target.ip_source.clone(),
+      target.value.clone(),
+      &target.record_type,
+    )?;
+    if self.published.as_ref() == Some(&value) {
+      return Ok(());
+    }
+
+    let result = sync_record(
+      target.provider.as_ref(),
+      &target.sub_domain,
+      &target.record_type,
+      &target.record_line,
+      &value,
+      &target.spec,
+    )?;
+    tracing::info!("{} -> {}: {}", target.sub_domain, value, result);
+    self.published = Some(value);
+    Ok(())
+  }
+}
+
+/// Retry delay after `failures` consecutive failed updates.
+fn backoff(failures: u32) -> Duration {
+  let shift = failures.saturating_sub(1).min(16);
+  BACKOFF_MIN
+    .checked_mul(1 << shift)
+    .map_or(BACKOFF_MAX, |d| d.min(BACKOFF_MAX))
+}
+
+struct Signals {
+  term: Arc<AtomicBool>,
+  hup: Arc<AtomicBool>,
+}
+
+impl Signals {
+  fn register() -> Result<Self> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::flag::register;
+
+    let term = Arc::new(AtomicBool::new(false));
+    let hup = Arc::new(AtomicBool::new(false));
+    register(SIGTERM, Arc::clone(&term))?;
+    register(SIGINT, Arc::clone(&term))?;
+    #[cfg(unix)]
+    register(signal_hook::consts::SIGHUP, Arc::clone(&hup))?;
+    Ok(Signals { term, hup })
+  }
+
+  fn terminated(&self) -> bool {
+    self.term.load(Ordering::Relaxed)
+  }
+
+  fn reload(&self) -> bool {
+    self.hup.swap(false, Ordering::Relaxed)
+  }
+
+  fn pending(&self) -> bool {
+    self.terminated() || self.hup.load(Ordering::Relaxed)
+  }
+}
+
+/// Poll the targets every `interval` until SIGTERM or SIGINT.
+///
+/// Targets taking their value from an interface are also updated shortly
+/// after the addresses of the interface change, when notified of it.
+///
+/// `load` builds the targets, it is called on start and again on SIGHUP so
+/// that keys and settings are re-read. When reloading fails the previous
+/// targets are kept.
+pub fn run<F>(interval: Duration, load: F) -> Result<()>
+where
+  F: Fn() -> Result<Vec<Target>>,
+{
+  let signals = Signals::register()?;
+  let mut watcher = Watcher::new();
+  let mut states: Vec<State> = load()?.into_iter().map(State::new).collect();
+
+  while !signals.terminated() {
+    if signals.reload() {
+      match load() {
+        Ok(targets) => {
+          tracing::info!("Configuration reloaded");
+          states = targets.into_iter().map(State::new).collect();
+        }
+        Err(err) => {
+          tracing::error!("Failed to reload configuration: {}", err);
+        }
+      }
+    }
+
+    let now = Instant::now();
+    for state in states.iter_mut().filter(|s| s.next <= now) {
+      match state.poll() {
+        Ok(()) => {
+          state.failures = 0;
+          state.next = now + interval;
+        }
+        Err(err) => {
+          state.failures += 1;
+          let delay = backoff(state.failures);
+          tracing::warn!(
+            "Failed to update {}: {}, retry in {:?}",
+            state.target.sub_domain,
+            err,
+            delay
+          );
+          state.next = now + delay;
+        }
+      }
+    }
+
+    let mut wake = states
+      .iter()
+      .map(|s| s.next)
+      .min()
+      .unwrap_or_else(|| Instant::now() + interval);
+    while !signals.pending() && Instant::now() < wake {
+      let changed =
+        watcher.wait(TICK.min(wake.saturating_duration_since(Instant::now())));
+      if changed.is_empty() {
+        continue;
+      }
+      tracing::debug!("Addresses changed: {:?}", changed);
+      let next = Instant::now() + DEBOUNCE;
+      for state in states.iter_mut().filter(|s| s.watches(&changed)) {
+        state.next = state.next.min(next);
+        wake = wake.min(next);
+      }
+    }
+  }
+
+  tracing::info!("Terminated");
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_backoff() {
+    assert_eq!(backoff(1), BACKOFF_MIN);
+    assert_eq!(backoff(2), BACKOFF_MIN * 2);
+    assert_eq!(backoff(4), BACKOFF_MIN * 8);
+    assert_eq!(backoff(100), BACKOFF_MAX);
+  }
+}
@@ -1,4 +1,9 @@
-use std::{array::TryFromSliceError, io, num::ParseIntError};
+use std::{
+  array::TryFromSliceError,
+  io,
+  net::AddrParseError,
+  num::{ParseIntError, TryFromIntError},
+};
 
 use reqwest::header::InvalidHeaderValue;
 
@@ -29,6 +34,10 @@ impl Error {
   pub fn http_failed(message: String) -> Self {
     Self::Reason(message)
   }
+
+  pub fn unsupported(action: &str) -> Self {
+    Self::Reason(format!("{} is not supported by the provider", action))
+  }
 }
 
 impl std::fmt::Display for Error {
@@ -81,6 +90,56 @@ impl From<TryFromSliceError> for Error {
   }
 }
 
+impl From<toml::de::Error> for Error {
+  fn from(error: toml::de::Error) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+impl From<serde_yaml::Error> for Error {
+  fn from(error: serde_yaml::Error) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+impl From<csv::Error> for Error {
+  fn from(error: csv::Error) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+impl From<AddrParseError> for Error {
+  fn from(error: AddrParseError) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+#[cfg(any(feature = "rfc2136", feature = "bind"))]
+impl From<trust_dns_proto::error::ProtoError> for Error {
+  fn from(error: trust_dns_proto::error::ProtoError) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+#[cfg(any(feature = "rfc2136", feature = "bind"))]
+impl From<trust_dns_proto::serialize::txt::ParseError> for Error {
+  fn from(error: trust_dns_proto::serialize::txt::ParseError) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+impl From<trust_dns_resolver::error::ResolveError> for Error {
+  fn from(error: trust_dns_resolver::error::ResolveError) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
+impl From<TryFromIntError> for Error {
+  fn from(error: TryFromIntError) -> Self {
+    Error::Catch(Box::new(error))
+  }
+}
+
 impl From<ParseIntError> for Error {
   fn from(error: ParseIntError) -> Self {
     Error::Catch(Box::new(error))
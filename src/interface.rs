@@ -1,57 +1,392 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+
 use crate::error::Error;
-use if_addrs::{get_if_addrs, IfAddr, Interface};
+use crate::source::{source_ip, Family};
+use if_addrs::{get_if_addrs, IfAddr};
+use ipnet::IpNet;
+use serde::Serialize;
 
-fn interface_ip(interface: String) -> Result<String, Error> {
-  let result = get_if_addrs()?;
+/// Host part put after the prefix of an interface address, so that hosts
+/// of the LAN follow a delegated prefix.
+#[derive(Debug, Clone, PartialEq)]
+struct HostSuffix {
+  suffix: Ipv6Addr,
+  /// Length of the prefix kept from the interface address.
+  prefix_len: u8,
+}
 
-  let opts: Vec<&str> = interface.split(',').map(|s| s.trim()).collect();
-  let name = opts[0];
+impl HostSuffix {
+  const DEFAULT_PREFIX_LEN: u8 = 64;
 
-  let filter_name = |i: Interface| {
-    if i.name.eq(name) && !i.is_loopback() {
-      Some(i.addr)
-    } else {
-      None
+  /// Parse `SUFFIX[/PREFIX_LEN]` where the suffix is an IPv6 address such as
+  /// `::1234:5678` or a MAC address whose EUI-64 is used.
+  fn parse(suffix: &str) -> Result<Self, Error> {
+    let (suffix, prefix_len) = match suffix.trim().split_once('/') {
+      Some((suffix, len)) => (suffix, len.trim().parse()?),
+      None => (suffix.trim(), Self::DEFAULT_PREFIX_LEN),
+    };
+    if prefix_len > 128 {
+      return Err(Error::Reason(format!("Bad prefix length: {}", prefix_len)));
     }
-  };
+    let suffix = match suffix.parse() {
+      Ok(suffix) => suffix,
+      Err(_) => eui64(parse_mac(suffix).ok_or_else(|| {
+        Error::Reason(format!("Bad IPv6 suffix or MAC address: {}", suffix))
+      })?),
+    };
+    Ok(HostSuffix { suffix, prefix_len })
+  }
+
+  /// Replace the host part of `address` with the suffix.
+  fn apply(&self, address: Ipv6Addr) -> Ipv6Addr {
+    let mask = u128::MAX
+      .checked_shl(128 - u32::from(self.prefix_len))
+      .unwrap_or(0);
+    Ipv6Addr::from(
+      (u128::from(address) & mask) | (u128::from(self.suffix) & !mask),
+    )
+  }
+}
+
+fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+  let parts: Vec<&str> = mac.split([':', '-']).collect();
+  if parts.len() != 6 {
+    return None;
+  }
+  let mut bytes = [0; 6];
+  for (byte, part) in bytes.iter_mut().zip(parts) {
+    *byte = u8::from_str_radix(part, 16).ok()?;
+  }
+  Some(bytes)
+}
+
+/// Modified EUI-64 interface identifier of a MAC address (RFC 4291).
+fn eui64(mac: [u8; 6]) -> Ipv6Addr {
+  let id = [
+    mac[0] ^ 0x02,
+    mac[1],
+    mac[2],
+    0xff,
+    0xfe,
+    mac[3],
+    mac[4],
+    mac[5],
+  ];
+  let mut octets = [0; 16];
+  octets[8..].copy_from_slice(&id);
+  Ipv6Addr::from(octets)
+}
+
+/// Keys of the structured selector.
+const SELECTOR_KEYS: &[&str] = &[
+  "family", "scope", "include", "exclude", "prefer", "index", "suffix",
+];
 
-  let filter_ip_type = if opts.len() >= 2 {
-    if opts[1].eq("4") {
-      |ip: &IfAddr| matches!(ip, IfAddr::V4(_))
-    } else if opts[1].eq("6") {
-      |ip: &IfAddr| matches!(ip, IfAddr::V6(_))
-    } else {
-      |_: &IfAddr| true
+/// Scope of an IPv6 address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+  Global,
+  /// Unique local address, `fc00::/7`.
+  Ula,
+  LinkLocal,
+}
+
+impl Scope {
+  /// Scope of `ip`, IPv4 addresses are not classified.
+  fn of(ip: &IpAddr) -> Option<Self> {
+    match ip {
+      IpAddr::V4(_) => None,
+      IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => {
+        Some(Scope::LinkLocal)
+      }
+      IpAddr::V6(ip) if ip.segments()[0] & 0xfe00 == 0xfc00 => Some(Scope::Ula),
+      IpAddr::V6(_) => Some(Scope::Global),
+    }
+  }
+
+  /// Order of preference when no scope is asked.
+  fn rank(scope: Option<Self>) -> u8 {
+    match scope {
+      None | Some(Scope::Global) => 0,
+      Some(Scope::Ula) => 1,
+      Some(Scope::LinkLocal) => 2,
+    }
+  }
+}
+
+/// Kernel flags of an IPv6 address, as found in `/proc/net/if_inet6`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct AddrFlags(u8);
+
+impl AddrFlags {
+  const TEMPORARY: u8 = 0x01;
+  const DADFAILED: u8 = 0x08;
+  const DEPRECATED: u8 = 0x20;
+  const TENTATIVE: u8 = 0x40;
+
+  /// Privacy extension address (RFC 8981).
+  fn temporary(self) -> bool {
+    self.0 & Self::TEMPORARY != 0
+  }
+
+  /// Whether the address must not be published.
+  fn unusable(self) -> bool {
+    self.0 & (Self::DADFAILED | Self::DEPRECATED | Self::TENTATIVE) != 0
+  }
+}
+
+/// Flags of the IPv6 addresses by interface name and address.
+#[cfg(target_os = "linux")]
+fn address_flags() -> HashMap<(String, Ipv6Addr), AddrFlags> {
+  let content = match std::fs::read_to_string("/proc/net/if_inet6") {
+    Ok(content) => content,
+    Err(err) => {
+      tracing::debug!("Failed to read address flags: {}", err);
+      return HashMap::new();
     }
-  } else {
-    |_: &IfAddr| true
   };
+  content
+    .lines()
+    .filter_map(|line| {
+      // address, index, prefix length, scope, flags, name
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let ip = Ipv6Addr::from(u128::from_str_radix(fields.first()?, 16).ok()?);
+      let flags = u8::from_str_radix(fields.get(4)?, 16).ok()?;
+      Some(((String::from(*fields.get(5)?), ip), AddrFlags(flags)))
+    })
+    .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn address_flags() -> HashMap<(String, Ipv6Addr), AddrFlags> {
+  HashMap::new()
+}
+
+/// Choice of an address among those of the interfaces.
+///
+/// Written `NAME?KEY=VALUE&...`, e.g. `eth0?family=6&exclude=fd00::/8`.
+/// The positional `NAME[,FAMILY[,PREFIX[,NTH]]][+SUFFIX]` is still accepted.
+#[derive(Debug, Default, PartialEq)]
+struct Selector {
+  name: String,
+  family: Option<Family>,
+  /// Scope of IPv6 addresses, any but link-local when unset.
+  scope: Option<Scope>,
+  /// Whether `any` scope was asked, link-local included.
+  any_scope: bool,
+  /// Networks the address must be in, any when empty.
+  include: Vec<IpNet>,
+  /// Networks the address must not be in.
+  exclude: Vec<IpNet>,
+  /// Text the address starts with, only set by the positional form.
+  text_prefix: Option<String>,
+  /// Put temporary addresses before the stable ones.
+  prefer_temporary: bool,
+  index: usize,
+  suffix: Option<HostSuffix>,
+}
 
-  let mut list = result
-    .into_iter()
-    .filter_map(filter_name)
-    .filter(filter_ip_type)
-    .map(|ip| match ip {
-      IfAddr::V4(ip) => format!("{}", ip.ip),
-      IfAddr::V6(ip) => format!("{}", ip.ip),
+fn parse_family(family: &str) -> Result<Option<Family>, Error> {
+  match family {
+    "4" => Ok(Some(Family::V4)),
+    "6" => Ok(Some(Family::V6)),
+    "" | "any" => Ok(None),
+    _ => Err(Error::Reason(format!(
+      "Bad family {}, expected 4, 6 or any",
+      family
+    ))),
+  }
+}
+
+fn parse_scope(scope: &str) -> Result<(Option<Scope>, bool), Error> {
+  match scope {
+    "global" => Ok((Some(Scope::Global), false)),
+    "ula" => Ok((Some(Scope::Ula), false)),
+    "link-local" => Ok((Some(Scope::LinkLocal), false)),
+    "any" => Ok((None, true)),
+    _ => Err(Error::Reason(format!(
+      "Bad scope {}, expected global, ula, link-local or any",
+      scope
+    ))),
+  }
+}
+
+fn parse_prefer(prefer: &str) -> Result<bool, Error> {
+  match prefer {
+    "temporary:yes" => Ok(true),
+    "temporary:no" => Ok(false),
+    _ => Err(Error::Reason(format!(
+      "Bad preference {}, expected temporary:yes or temporary:no",
+      prefer
+    ))),
+  }
+}
+
+fn parse_nets(nets: &str) -> Result<Vec<IpNet>, Error> {
+  nets
+    .split(',')
+    .map(|net| {
+      let net = net.trim();
+      net
+        .parse()
+        .or_else(|_| net.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| Error::Reason(format!("Bad network: {}", net)))
     })
-    .filter(|s| opts.len() < 3 || s.starts_with(opts[2]));
+    .collect()
+}
 
-  let nth = if opts.len() >= 4 {
-    let n = opts[3].parse().unwrap_or(0);
-    list.nth(n)
-  } else {
-    list.next()
-  };
-  nth.ok_or_else(|| Error::Reason(format!("No such interface: {}", interface)))
+impl Selector {
+  fn parse(selector: &str) -> Result<Self, Error> {
+    match selector.split_once('?') {
+      Some((name, query)) => Self::parse_query(name, query),
+      None => Self::parse_positional(selector),
+    }
+  }
+
+  fn parse_query(name: &str, query: &str) -> Result<Self, Error> {
+    let mut selector = Selector {
+      name: String::from(name.trim()),
+      ..Selector::default()
+    };
+    for pair in query.split('&').filter(|p| !p.trim().is_empty()) {
+      let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+      let value = value.trim();
+      match key.trim() {
+        "family" => selector.family = parse_family(value)?,
+        "scope" => {
+          let (scope, any_scope) = parse_scope(value)?;
+          selector.scope = scope;
+          selector.any_scope = any_scope;
+        }
+        "prefer" => selector.prefer_temporary = parse_prefer(value)?,
+        "include" => selector.include.extend(parse_nets(value)?),
+        "exclude" => selector.exclude.extend(parse_nets(value)?),
+        "index" => selector.index = value.parse()?,
+        "suffix" => selector.suffix = Some(HostSuffix::parse(value)?),
+        key => {
+          return Err(Error::Reason(format!(
+            "Unknown key {:?} in interface selector, valid keys are: {}",
+            key,
+            SELECTOR_KEYS.join(", ")
+          )))
+        }
+      }
+    }
+    Ok(selector)
+  }
+
+  fn parse_positional(selector: &str) -> Result<Self, Error> {
+    let (selector, suffix) = match selector.split_once('+') {
+      Some((selector, suffix)) => (selector, Some(HostSuffix::parse(suffix)?)),
+      None => (selector, None),
+    };
+    let opts: Vec<&str> = selector.split(',').map(|s| s.trim()).collect();
+    Ok(Selector {
+      name: String::from(opts[0]),
+      // anything else than 4 or 6 used to mean any family
+      family: opts.get(1).and_then(|f| parse_family(f).ok()).flatten(),
+      text_prefix: opts
+        .get(2)
+        .filter(|p| !p.is_empty())
+        .map(|p| String::from(*p)),
+      index: opts.get(3).map_or(0, |n| n.parse().unwrap_or(0)),
+      suffix,
+      ..Selector::default()
+    })
+  }
+
+  fn accept(&self, name: &str, ip: &IpAddr, flags: AddrFlags) -> bool {
+    let scope = Scope::of(ip);
+    name == self.name
+      && !ip.is_loopback()
+      && !flags.unusable()
+      && self.family.is_none_or(|family| family.matches(ip))
+      && match (self.scope, scope) {
+        (Some(wanted), Some(scope)) => wanted == scope,
+        (None, Some(Scope::LinkLocal)) => self.any_scope,
+        _ => true,
+      }
+      && (self.include.is_empty()
+        || self.include.iter().any(|net| net.contains(ip)))
+      && !self.exclude.iter().any(|net| net.contains(ip))
+      && self
+        .text_prefix
+        .as_ref()
+        .is_none_or(|prefix| ip.to_string().starts_with(prefix))
+  }
+
+  /// Pick the address among the `(interface name, address, flags)` of the
+  /// interfaces.
+  ///
+  /// Stable addresses come before temporary ones unless preferred otherwise,
+  /// then global addresses before unique local and link-local ones.
+  fn select<'a, I>(&self, addrs: I) -> Option<IpAddr>
+  where
+    I: IntoIterator<Item = (&'a str, IpAddr, AddrFlags)>,
+  {
+    let mut addrs: Vec<(IpAddr, AddrFlags)> = addrs
+      .into_iter()
+      .filter(|(name, ip, flags)| self.accept(name, ip, *flags))
+      .map(|(_, ip, flags)| (ip, flags))
+      .collect();
+    addrs.sort_by_key(|(ip, flags)| {
+      (
+        flags.temporary() != self.prefer_temporary,
+        Scope::rank(Scope::of(ip)),
+      )
+    });
+    addrs
+      .into_iter()
+      .filter_map(|(ip, _)| match (ip, &self.suffix) {
+        (ip, None) => Some(ip),
+        // link-local addresses do not carry the delegated prefix
+        (IpAddr::V6(ip), Some(suffix))
+          if ip.segments()[0] & 0xffc0 != 0xfe80 =>
+        {
+          Some(IpAddr::V6(suffix.apply(ip)))
+        }
+        _ => None,
+      })
+      .nth(self.index)
+  }
+}
+
+/// Name of the interface an address is chosen from by the selector.
+pub fn selector_interface(selector: &str) -> Result<String, Error> {
+  Ok(Selector::parse(selector)?.name)
+}
+
+/// Address of the interface chosen by the selector, see `Selector`.
+fn interface_ip(interface: String) -> Result<String, Error> {
+  let selector = Selector::parse(&interface)?;
+  let addrs = get_if_addrs()?;
+  let flags = address_flags();
+  selector
+    .select(addrs.iter().map(|i| {
+      let ip = i.ip();
+      let flag = match ip {
+        IpAddr::V6(ip) => flags.get(&(i.name.clone(), ip)).copied(),
+        IpAddr::V4(_) => None,
+      };
+      (i.name.as_str(), ip, flag.unwrap_or_default())
+    }))
+    .map(|ip| ip.to_string())
+    .ok_or_else(|| Error::Reason(format!("No such interface: {}", interface)))
 }
 
+/// Value of a record of `record_type`: the given value, else the address
+/// found by the source, else the address of the interface.
 pub fn interface_or_value(
   interface: Option<String>,
+  ip_source: Option<String>,
   value: Option<String>,
+  record_type: &str,
 ) -> Result<String, Error> {
   if let Some(value) = value {
     Ok(value)
+  } else if let Some(ip_source) = ip_source {
+    Ok(source_ip(&ip_source, Family::of_record(record_type))?.to_string())
   } else {
     let interface =
       interface.ok_or(Error::Reason(String::from("Missing argument")))?;
@@ -59,18 +394,167 @@ pub fn interface_or_value(
   }
 }
 
+/// An address of an interface, as listed by `cao interface`.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct InterfaceAddr {
+  pub name: String,
+  pub address: IpAddr,
+  /// Length of the network prefix, unknown for a selected address.
+  pub prefix_len: Option<u8>,
+}
+
+/// Every address of the interfaces, or the one chosen by a selector.
 pub fn interface_list(
   interface: Option<String>,
-) -> Result<Vec<(String, String)>, Error> {
+) -> Result<Vec<InterfaceAddr>, Error> {
   if let Some(interface) = interface {
-    let s = interface_ip(interface.clone())?;
-    Ok(vec![(interface, s)])
+    let address = interface_ip(interface.clone())?;
+    Ok(vec![InterfaceAddr {
+      name: selector_interface(&interface)?,
+      address: address.parse()?,
+      prefix_len: None,
+    }])
   } else {
     Ok(
       get_if_addrs()?
         .into_iter()
-        .map(|i| (i.name, format!("{:?}", i.addr)))
+        .map(|i| {
+          let (address, prefix_len) = match i.addr {
+            IfAddr::V4(addr) => {
+              (IpAddr::V4(addr.ip), u32::from(addr.netmask).count_ones())
+            }
+            IfAddr::V6(addr) => {
+              (IpAddr::V6(addr.ip), u128::from(addr.netmask).count_ones())
+            }
+          };
+          InterfaceAddr {
+            name: i.name,
+            address,
+            prefix_len: Some(prefix_len as u8),
+          }
+        })
         .collect(),
     )
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_host_suffix() {
+    let address: Ipv6Addr =
+      "2001:db8:1234:5600:aaaa:bbbb:cccc:dddd".parse().unwrap();
+
+    let suffix = HostSuffix::parse("::1234:5678").unwrap();
+    assert_eq!(
+      suffix.apply(address),
+      "2001:db8:1234:5600::1234:5678".parse::<Ipv6Addr>().unwrap()
+    );
+
+    let suffix = HostSuffix::parse("::ff:0:0:0:1/56").unwrap();
+    assert_eq!(
+      suffix.apply(address),
+      "2001:db8:1234:56ff::1".parse::<Ipv6Addr>().unwrap()
+    );
+
+    let suffix = HostSuffix::parse("52:54:00:12:34:56").unwrap();
+    assert_eq!(
+      suffix.apply(address),
+      "2001:db8:1234:5600:5054:ff:fe12:3456"
+        .parse::<Ipv6Addr>()
+        .unwrap()
+    );
+
+    assert!(HostSuffix::parse("::1/129").is_err());
+    assert!(HostSuffix::parse("52:54:00:12:34").is_err());
+  }
+
+  #[test]
+  fn test_selector() {
+    let none = AddrFlags::default();
+    let addrs: Vec<(&str, IpAddr, AddrFlags)> = vec![
+      ("lo", "127.0.0.1".parse().unwrap(), none),
+      ("eth0", "192.168.1.2".parse().unwrap(), none),
+      ("eth0", "fe80::1".parse().unwrap(), none),
+      ("eth0", "fd00::2".parse().unwrap(), none),
+      ("eth0", "2001:db8::3".parse().unwrap(), none),
+      ("eth1", "10.0.0.1".parse().unwrap(), none),
+      ("eth2", "fe80::5054:ff:fe12:3456".parse().unwrap(), none),
+    ];
+    let select = |selector: &str| {
+      Selector::parse(selector)
+        .unwrap()
+        .select(addrs.clone())
+        .map(|ip| ip.to_string())
+    };
+
+    assert_eq!(select("eth0").as_deref(), Some("192.168.1.2"));
+    assert_eq!(select("eth0,6").as_deref(), Some("2001:db8::3"));
+    assert_eq!(select("eth0,6,fd").as_deref(), Some("fd00::2"));
+    assert_eq!(select("eth0,6,,1").as_deref(), Some("fd00::2"));
+    assert_eq!(select("eth0,6,,2"), None);
+    // link-local addresses are not published unless asked for
+    assert_eq!(select("eth2,6"), None);
+    assert_eq!(select("eth2"), None);
+    assert_eq!(
+      select("eth2?scope=any").as_deref(),
+      Some("fe80::5054:ff:fe12:3456")
+    );
+    assert_eq!(
+      select("eth0?family=6&exclude=2000::/3").as_deref(),
+      Some("fd00::2")
+    );
+    assert_eq!(
+      select("eth0?include=fc00::/7&suffix=::9").as_deref(),
+      Some("fd00::9")
+    );
+    assert_eq!(
+      select("eth0?family=6&scope=any&index=2").as_deref(),
+      Some("fe80::1")
+    );
+    assert_eq!(
+      select("eth0?scope=link-local&family=6").as_deref(),
+      Some("fe80::1")
+    );
+    assert_eq!(
+      select("eth0?scope=ula&family=6").as_deref(),
+      Some("fd00::2")
+    );
+    assert_eq!(select("lo?family=4"), None);
+    assert_eq!(
+      Selector::parse("eth0,6+::1").unwrap(),
+      Selector::parse("eth0?family=6&suffix=::1").unwrap()
+    );
+
+    let err = Selector::parse("eth0?state=up").unwrap_err();
+    assert!(err.to_string().contains("family, scope"));
+    assert!(Selector::parse("eth0?include=10.0.0.0/33").is_err());
+    assert!(Selector::parse("eth0?scope=site").is_err());
+  }
+
+  #[test]
+  fn test_selector_flags() {
+    let addrs: Vec<(&str, IpAddr, AddrFlags)> = vec![
+      ("eth0", "2001:db8::1".parse().unwrap(), AddrFlags(0x20)),
+      ("eth0", "2001:db8::2".parse().unwrap(), AddrFlags(0x01)),
+      ("eth0", "2001:db8::3".parse().unwrap(), AddrFlags(0x40)),
+      ("eth0", "2001:db8::4".parse().unwrap(), AddrFlags(0x80)),
+    ];
+    let select = |selector: &str| {
+      Selector::parse(selector)
+        .unwrap()
+        .select(addrs.clone())
+        .map(|ip| ip.to_string())
+    };
+
+    assert_eq!(select("eth0,6").as_deref(), Some("2001:db8::4"));
+    assert_eq!(select("eth0,6,,1").as_deref(), Some("2001:db8::2"));
+    assert_eq!(
+      select("eth0?prefer=temporary:yes").as_deref(),
+      Some("2001:db8::2")
+    );
+    assert_eq!(select("eth0?index=2"), None);
+  }
+}
@@ -1,17 +1,40 @@
 mod args;
+mod config;
+mod daemon;
 mod error;
 mod interface;
+mod migrate;
+#[cfg(test)]
+mod mock;
+mod output;
+mod plan;
 mod provider;
+mod source;
+mod sync;
+mod watch;
+mod zone;
 
 #[cfg(all(feature = "ureq", feature = "curl"))]
 compile_error!(
   "Features `ureq' and `curl' cannot be enabled at the same time."
 );
 
-use crate::args::{Args, RecordCmds};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::args::{Args, Cmds, RecordCmds, ZoneCmds};
+use crate::config::{Config, DEFAULT_CONFIG};
 use crate::error::Error;
 use crate::interface::{interface_list, interface_or_value};
+use crate::migrate::migrate;
+use crate::output::{print_all, print_one, Outcome};
+use crate::plan::plan_config;
 use crate::provider::build_dns_provider;
+use crate::provider::interface::{
+  find_record, list_all, RecordFilter, RecordSpec, RecordStatus, ValueMatch,
+  LIST_PAGE_SIZE,
+};
+use crate::sync::{find_ids, sync_record};
 
 fn fetch_key(file_name: String) -> Result<String, Error> {
   use std::fs::File;
@@ -28,19 +51,24 @@ fn main() -> Result<(), Error> {
   let args = Args::get_args();
 
   match args {
-    Err(err) => {
-      eprintln!("{}", err);
-    }
-    Ok(param) => {
-      match param {
-        Args::Record {
+    // exits with 0 for --help and --version only, so that usage errors are
+    // not taken for an unchanged record
+    Err(err) => err.exit(),
+    Ok(Args {
+      config,
+      output,
+      dry_run,
+      cmd,
+    }) => {
+      match cmd {
+        Cmds::Record {
           provider,
           key,
           domain,
           cmd,
         } => {
           let key = fetch_key(key)?;
-          let provider = build_dns_provider(&provider, key, domain)?;
+          let provider = build_dns_provider(&provider, key, domain, dry_run)?;
           match cmd {
             RecordCmds::Add {
               sub_domain,
@@ -48,25 +76,82 @@ fn main() -> Result<(), Error> {
               record_line,
               value,
               interface,
+              ip_source,
+              spec,
             } => {
-              let value = interface_or_value(interface, value)?;
+              let value =
+                interface_or_value(interface, ip_source, value, &record_type)?;
               let id = provider.add_record(
                 &sub_domain,
                 &record_type,
                 &record_line,
                 &value,
+                &spec.into(),
               )?;
-              print!("{}", id);
+              // the changes of a dry run are not made
+              let record = if dry_run {
+                None
+              } else {
+                find_record(provider.as_ref(), &id, Some(&sub_domain))?
+              };
+              let outcome = Outcome::changed(
+                "added",
+                id,
+                Some(&sub_domain),
+                &record_type,
+                &record_line,
+                &value,
+              )
+              .with_record(record);
+              print_one(output, &outcome)?;
             }
             RecordCmds::List {
               offset,
               length,
               sub_domain,
+              record_type,
+              record_line,
+              value,
+              value_contains,
+              value_in,
+              status,
+              keyword,
+              all,
             } => {
-              let records =
-                provider.list_record(offset, length, sub_domain.as_deref())?;
-              for record in records {
-                println!("{}", record);
+              let filter = RecordFilter {
+                sub_domain,
+                record_type,
+                record_line,
+                value: value
+                  .map(ValueMatch::Exact)
+                  .or_else(|| value_contains.map(ValueMatch::Contains))
+                  .or_else(|| value_in.map(ValueMatch::Network)),
+                status,
+                keyword,
+              };
+              if all {
+                let page = list_all(provider.as_ref(), &filter)?;
+                print_all(output, &page.records)?;
+                if let Some(total) = page.total {
+                  eprintln!("{} records in total", total);
+                }
+              } else {
+                let offset = offset.unwrap_or(0).max(0) as usize;
+                let mut page = provider.list_record_page(
+                  offset,
+                  length.map_or(LIST_PAGE_SIZE, |l| l.max(0) as usize),
+                  &filter,
+                )?;
+                let fetched = offset + page.records.len();
+                page.records.retain(|record| filter.matches(record));
+                print_all(output, &page.records)?;
+                if let Some(total) = page.total.filter(|t| *t > fetched) {
+                  eprintln!(
+                    "{} of {} records searched, use --all to list every \
+                     record",
+                    fetched, total
+                  );
+                }
               }
             }
             RecordCmds::Modify {
@@ -76,25 +161,261 @@ fn main() -> Result<(), Error> {
               record_line,
               value,
               interface,
+              ip_source,
+              spec,
+              all,
+            } => {
+              let value =
+                interface_or_value(interface, ip_source, value, &record_type)?;
+              let spec = spec.into();
+              let ids = match &record_id {
+                Some(id) => vec![id.clone()],
+                // checked by clap
+                None => find_ids(
+                  provider.as_ref(),
+                  sub_domain.as_deref().unwrap(),
+                  Some(&record_type),
+                  Some(&record_line),
+                  all,
+                )?,
+              };
+              let mut outcomes = Vec::new();
+              for id in ids {
+                let id = provider.modify_record(
+                  &id,
+                  sub_domain.as_deref(),
+                  &record_type,
+                  &record_line,
+                  &value,
+                  &spec,
+                )?;
+                let record = if dry_run {
+                  None
+                } else {
+                  find_record(provider.as_ref(), &id, sub_domain.as_deref())?
+                };
+                outcomes.push(
+                  Outcome::changed(
+                    "modified",
+                    id,
+                    sub_domain.as_deref(),
+                    &record_type,
+                    &record_line,
+                    &value,
+                  )
+                  .with_record(record),
+                );
+              }
+              match record_id {
+                Some(_) => print_one(output, &outcomes[0])?,
+                None => print_all(output, &outcomes)?,
+              }
+            }
+            RecordCmds::Delete {
+              record_id: Some(record_id),
+              ..
+            } => {
+              let record = find_record(provider.as_ref(), &record_id, None)?;
+              provider.delete_record(&record_id)?;
+              print_one(
+                output,
+                &Outcome::deleted(record_id).with_record(record),
+              )?;
+            }
+            RecordCmds::Delete {
+              record_id: None,
+              sub_domain,
+              record_type,
+              record_line,
+              all,
+            } => {
+              // checked by clap
+              let ids = find_ids(
+                provider.as_ref(),
+                sub_domain.as_deref().unwrap(),
+                record_type.as_deref(),
+                record_line.as_deref(),
+                all,
+              )?;
+              let mut outcomes = Vec::new();
+              for id in ids {
+                let record =
+                  find_record(provider.as_ref(), &id, sub_domain.as_deref())?;
+                provider.delete_record(&id)?;
+                outcomes.push(Outcome::deleted(id).with_record(record));
+              }
+              print_all(output, &outcomes)?;
+            }
+            RecordCmds::Enable { record_id } => {
+              provider.set_record_status(&record_id, RecordStatus::Enabled)?;
+              print_one(
+                output,
+                &Outcome::status(record_id, RecordStatus::Enabled),
+              )?;
+            }
+            RecordCmds::Disable { record_id } => {
+              provider.set_record_status(&record_id, RecordStatus::Disabled)?;
+              print_one(
+                output,
+                &Outcome::status(record_id, RecordStatus::Disabled),
+              )?;
+            }
+            RecordCmds::Sync {
+              sub_domain,
+              record_type,
+              record_line,
+              value,
+              interface,
+              ip_source,
+              spec,
             } => {
-              let value = interface_or_value(interface, value)?;
-              provider.modify_record(
-                record_id,
-                sub_domain.as_deref(),
+              let value =
+                interface_or_value(interface, ip_source, value, &record_type)?;
+              let result = sync_record(
+                provider.as_ref(),
+                &sub_domain,
                 &record_type,
                 &record_line,
                 &value,
+                &spec.into(),
               )?;
-            }
-            RecordCmds::Delete { record_id } => {
-              provider.delete_record(record_id)?;
+              let outcome = Outcome::changed(
+                result.action(),
+                String::from(result.id()),
+                Some(&sub_domain),
+                &record_type,
+                &record_line,
+                &value,
+              );
+              print_one(output, &outcome)?;
+              std::process::exit(result.exit_code());
             } // _ => unimplemented!("Unimplemented option: {:?}", param.cmd),
           }
         }
-        Args::Interface { interface } => {
-          for (n, i) in interface_list(interface)? {
-            eprintln!("{}: {}", n, i);
+        Cmds::Zone {
+          provider,
+          key,
+          domain,
+          cmd,
+        } => {
+          let key = fetch_key(key)?;
+          let provider =
+            build_dns_provider(&provider, key, domain.clone(), dry_run)?;
+          match cmd {
+            ZoneCmds::Export { format } => {
+              zone::export(provider.as_ref(), &domain, format)?
+            }
+            ZoneCmds::Import {
+              file,
+              format,
+              record_line,
+            } => zone::import(
+              provider.as_ref(),
+              &domain,
+              &file,
+              format,
+              record_line.as_deref(),
+              output,
+            )?,
           }
+        }
+        Cmds::Daemon {
+          provider,
+          key,
+          domain,
+          sub_domain,
+          record_type,
+          record_line,
+          value,
+          interface,
+          ip_source,
+          interval,
+        } => {
+          let load = || {
+            if let Some(config) = &config {
+              let config = Config::load(config)?;
+              let mut targets = Vec::new();
+              for (provider, domain) in config.build(dry_run)? {
+                for record in &domain.records {
+                  targets.push(daemon::Target {
+                    provider: Rc::clone(&provider),
+                    sub_domain: record.sub_domain.clone(),
+                    record_type: record.record_type.clone(),
+                    record_line: record.record_line.clone(),
+                    value: record.value.clone(),
+                    interface: record.interface.clone(),
+                    ip_source: record.ip_source.clone(),
+                    spec: record.spec(),
+                  });
+                }
+              }
+              return Ok(targets);
+            }
+
+            // checked by `Args::get_args`
+            let key = fetch_key(key.clone().unwrap())?;
+            let provider = build_dns_provider(
+              provider.as_deref().unwrap(),
+              key,
+              domain.clone().unwrap(),
+              dry_run,
+            )?;
+            Ok(vec![daemon::Target {
+              provider: Rc::from(provider),
+              sub_domain: sub_domain.clone().unwrap(),
+              record_type: record_type.clone().unwrap(),
+              record_line: record_line.clone().unwrap(),
+              value: value.clone(),
+              interface: interface.clone(),
+              ip_source: ip_source.clone(),
+              spec: RecordSpec::default(),
+            }])
+          };
+          daemon::run(Duration::from_secs(interval), load)?;
+        }
+        Cmds::Plan { prune } => {
+          let config =
+            Config::load(config.as_deref().unwrap_or(DEFAULT_CONFIG))?;
+          plan_config(&config, prune, false, dry_run, output)?;
+        }
+        Cmds::Apply { prune } => {
+          let config =
+            Config::load(config.as_deref().unwrap_or(DEFAULT_CONFIG))?;
+          plan_config(&config, prune, true, dry_run, output)?;
+        }
+        Cmds::Migrate {
+          from,
+          from_key,
+          to,
+          to_key,
+          line_maps,
+          prune,
+        } => {
+          let source = build_dns_provider(
+            &from.provider,
+            fetch_key(from_key)?,
+            from.domain.clone(),
+            false,
+          )?;
+          let target = build_dns_provider(
+            &to.provider,
+            fetch_key(to_key)?,
+            to.domain.clone(),
+            dry_run,
+          )?;
+          migrate(
+            source.as_ref(),
+            &from,
+            target.as_ref(),
+            &to,
+            &line_maps,
+            prune,
+            dry_run,
+            output,
+          )?;
+        }
+        Cmds::Interface { interface } => {
+          print_all(output, &interface_list(interface)?)?;
         } // _ => unimplemented!("Unimplemented option: {:?}", param.cmd),
       };
     }
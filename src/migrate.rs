@@ -0,0 +1,373 @@
+//! Migration of every record of a domain from a provider to another.
+//!
+//! Records are translated to the lines and types of the target, the ones
+//! without counterpart are reported and skipped. The target is then brought
+//! to the translated records as `apply` does, and listed again to check that
+//! no difference is left.
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::output::{print_all, Format, Tabular};
+use crate::plan::{apply, is_zone_record, plan, Desired};
+use crate::provider::interface::{
+  list_all, DnsProvider, Record, RecordFilter, RecordSpec, RecordStatus,
+};
+use crate::zone::zone_name;
+
+/// A domain of a provider, given as `PROVIDER:DOMAIN`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+  pub provider: String,
+  pub domain: String,
+}
+
+impl FromStr for Endpoint {
+  type Err = String;
+
+  fn from_str(endpoint: &str) -> std::result::Result<Self, Self::Err> {
+    match endpoint.split_once(':') {
+      Some((provider, domain))
+        if !provider.is_empty() && !domain.is_empty() =>
+      {
+        Ok(Endpoint {
+          provider: String::from(provider),
+          domain: String::from(domain),
+        })
+      }
+      _ => Err(format!("Expected PROVIDER:DOMAIN, got {}", endpoint)),
+    }
+  }
+}
+
+/// Line of the target for a line of the source, given as `FROM=TO`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMap {
+  pub from: String,
+  pub to: String,
+}
+
+impl FromStr for LineMap {
+  type Err = String;
+
+  fn from_str(line_map: &str) -> std::result::Result<Self, Self::Err> {
+    match line_map.split_once('=') {
+      Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok(LineMap {
+        from: String::from(from),
+        to: String::from(to),
+      }),
+      _ => Err(format!("Expected FROM=TO, got {}", line_map)),
+    }
+  }
+}
+
+/// Record with the trailing dot of names removed and the priority of MX
+/// records in its own field, as providers list them either way.
+fn normalize(mut record: Record) -> Record {
+  let r_type = record.r_type.to_uppercase();
+  if r_type == "MX" {
+    if let Some((mx, exchange)) = record.value.split_once(' ') {
+      if let Ok(mx) = mx.parse() {
+        record.mx = Some(mx);
+        record.value = String::from(exchange);
+      }
+    }
+  }
+  if matches!(
+    r_type.as_str(),
+    "CNAME" | "MX" | "NS" | "PTR" | "DNAME" | "SRV"
+  ) {
+    record.value = String::from(record.value.trim_end_matches('.'));
+  }
+  record
+}
+
+/// The normalized record of the source as it should be on the target, or
+/// why it is skipped.
+///
+/// Lines and types are translated by their names on DNSPod, unless both
+/// are of the same provider.
+pub fn translate(
+  record: &Record,
+  source: &dyn DnsProvider,
+  target: &dyn DnsProvider,
+  same_provider: bool,
+  line_maps: &[LineMap],
+) -> std::result::Result<Desired, String> {
+  if is_zone_record(&record.sub_domain, &record.r_type)
+    || record.r_type.eq_ignore_ascii_case("SOA")
+  {
+    return Err(String::from("managed by the provider"));
+  }
+  let record_type = if same_provider {
+    Some(record.r_type.clone())
+  } else {
+    source
+      .common_type(&record.r_type)
+      .and_then(|r_type| target.provider_type(&r_type))
+  }
+  .ok_or_else(|| format!("no type {} on the target", record.r_type))?;
+  let record_line = match line_maps.iter().find(|m| m.from == record.r_line) {
+    Some(line_map) => Some(line_map.to.clone()),
+    None if same_provider => Some(record.r_line.clone()),
+    None => source
+      .common_line(&record.r_line)
+      .and_then(|line| target.provider_line(&line)),
+  }
+  .ok_or_else(|| {
+    format!("no line {} on the target, see --line-map", record.r_line)
+  })?;
+
+  if record.status == Some(RecordStatus::Disabled) && !target.supports_status()
+  {
+    return Err(String::from("disabled, no disabled records on the target"));
+  }
+  let fields = target.spec_fields();
+  let field = |name: &str| fields.contains(&name);
+  let spec = RecordSpec {
+    ttl: record.ttl.filter(|_| field("ttl")),
+    mx: record
+      .mx
+      .filter(|_| field("mx") && record_type.eq_ignore_ascii_case("MX")),
+    weight: record.weight.filter(|_| field("weight")),
+    status: record.status.filter(|_| target.supports_status()),
+    remark: record.remark.clone().filter(|_| field("remark")),
+  };
+  Ok(Desired {
+    sub_domain: record.sub_domain.clone(),
+    value: Some(record.value.clone()),
+    record_type,
+    record_line,
+    spec,
+  })
+}
+
+/// Records of the target with normalized values, to compare them with the
+/// translated ones.
+fn target_records(target: &dyn DnsProvider) -> Result<Vec<Record>> {
+  let records = list_all(target, &RecordFilter::default())?.records;
+  Ok(records.into_iter().map(normalize).collect())
+}
+
+/// Copy every record of `source` to `target`, deleting the other records of
+/// the target with `prune`.
+///
+/// The steps are printed. With `dry_run` the target only logs the changes,
+/// otherwise the target is listed again and the differences left are
+/// reported as an error.
+#[allow(clippy::too_many_arguments)]
+pub fn migrate(
+  source: &dyn DnsProvider,
+  from: &Endpoint,
+  target: &dyn DnsProvider,
+  to: &Endpoint,
+  line_maps: &[LineMap],
+  prune: bool,
+  dry_run: bool,
+  output: Format,
+) -> Result<()> {
+  let mut desired = Vec::new();
+  let mut skipped = 0;
+  let same_provider = from.provider == to.provider;
+  let records = list_all(source, &RecordFilter::default())?.records;
+  for record in records.into_iter().map(normalize) {
+    match translate(&record, source, target, same_provider, line_maps) {
+      Ok(record) => desired.push(record),
+      Err(reason) => {
+        skipped += 1;
+        eprintln!(
+          "{} {} {} {}: skipped, {}",
+          record.sub_domain, record.r_type, record.r_line, record.value, reason
+        );
+      }
+    }
+  }
+
+  let domain = zone_name(&to.domain);
+  let planned = plan(domain, &target_records(target)?, &desired, prune);
+  if planned.unmanaged > 0 {
+    eprintln!(
+      "{}: {} record(s) not in the source kept, use --prune to delete them",
+      domain, planned.unmanaged
+    );
+  }
+  let failed = apply(target, &planned.steps);
+  if planned.steps.is_empty() {
+    eprintln!("No changes");
+  } else {
+    print_all(output, &planned.steps)?;
+  }
+  if skipped > 0 {
+    eprintln!("{} record(s) skipped", skipped);
+  }
+
+  if dry_run {
+    return Ok(());
+  }
+  let left = plan(domain, &target_records(target)?, &desired, prune);
+  for step in &left.steps {
+    eprintln!("differs after migration: {}", step.plain());
+  }
+  if failed > 0 {
+    Err(Error::Reason(format!("{} record(s) failed", failed)))
+  } else if !left.steps.is_empty() {
+    Err(Error::Reason(format!(
+      "{} difference(s) left on {}",
+      left.steps.len(),
+      domain
+    )))
+  } else {
+    eprintln!("{} record(s) verified on {}", desired.len(), domain);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::memory;
+
+  fn record(
+    sub_domain: &str,
+    r_type: &str,
+    r_line: &str,
+    value: &str,
+  ) -> Record {
+    Record {
+      r_line: String::from(r_line),
+      ..memory::record("", sub_domain, r_type, value)
+    }
+  }
+
+  fn with_ids(mut records: Vec<Record>) -> Vec<Record> {
+    for (i, record) in records.iter_mut().enumerate() {
+      record.id = (i + 1).to_string();
+    }
+    records
+  }
+
+  #[test]
+  fn test_translate() {
+    let dnspod = memory::Provider::default();
+    let no_lines = memory::Provider::without_lines(vec![]);
+    let translated = |record: &Record, line_maps: &[LineMap]| {
+      translate(
+        &normalize(record.clone()),
+        &dnspod,
+        &no_lines,
+        false,
+        line_maps,
+      )
+      .map(|d| {
+        format!("{} {} {}", d.record_type, d.record_line, d.value.unwrap())
+      })
+    };
+
+    let mut mx = record("@", "MX", "默认", "mail.example.com.");
+    mx.mx = Some(10);
+    mx.weight = Some(5);
+    assert_eq!(translated(&mx, &[]).unwrap(), "MX default mail.example.com");
+    let desired = translate(&mx, &dnspod, &no_lines, false, &[]).unwrap();
+    assert_eq!((desired.spec.mx, desired.spec.weight), (Some(10), None));
+
+    let telecom = record("www", "A", "电信", "1.2.3.4");
+    assert!(translated(&telecom, &[]).is_err());
+    let line_maps = vec!["电信=default".parse::<LineMap>().unwrap()];
+    assert_eq!(
+      translated(&telecom, &line_maps).unwrap(),
+      "A default 1.2.3.4"
+    );
+    let desired = translate(&telecom, &dnspod, &dnspod, true, &[]).unwrap();
+    assert_eq!(desired.record_line, "电信");
+
+    assert!(
+      translated(&record("@", "NS", "默认", "ns1.dnspod.net."), &[]).is_err()
+    );
+    let mut disabled = record("old", "A", "默认", "1.2.3.4");
+    disabled.status = Some(RecordStatus::Disabled);
+    assert!(translated(&disabled, &[]).is_err());
+    assert!(translate(&disabled, &no_lines, &dnspod, false, &[]).is_err());
+    let disabled = Record {
+      r_line: String::from("default"),
+      ..disabled
+    };
+    let desired = translate(&disabled, &no_lines, &dnspod, false, &[]).unwrap();
+    assert_eq!(desired.record_line, "默认");
+    assert_eq!(desired.spec.status, Some(RecordStatus::Disabled));
+
+    let mut mx = record("@", "MX", "default", "20 mx.example.com.");
+    mx.mx = None;
+    let mx = normalize(mx);
+    assert_eq!((mx.value.as_str(), mx.mx), ("mx.example.com", Some(20)));
+
+    assert_eq!(
+      "rfc2136:example.com@127.0.0.1:53"
+        .parse::<Endpoint>()
+        .unwrap(),
+      Endpoint {
+        provider: String::from("rfc2136"),
+        domain: String::from("example.com@127.0.0.1:53"),
+      }
+    );
+    assert!("example.com".parse::<Endpoint>().is_err());
+  }
+
+  #[test]
+  fn test_migrate() {
+    let mut mx = record("@", "MX", "默认", "mail.example.com.");
+    mx.mx = Some(10);
+    let source = memory::Provider::with_records(with_ids(vec![
+      record("@", "NS", "默认", "f1g1ns1.dnspod.net."),
+      record("www", "A", "默认", "1.2.3.4"),
+      record("www", "A", "电信", "2.3.4.5"),
+      record("blog", "CNAME", "默认", "example.github.io."),
+      mx,
+    ]));
+    let target = memory::Provider::without_lines(with_ids(vec![
+      record("www", "A", "default", "9.9.9.9"),
+      record("blog", "CNAME", "default", "example.github.io"),
+      record("old", "A", "default", "5.6.7.8"),
+    ]));
+    let from = "dnspod:example.com".parse().unwrap();
+    let to = "cloudflare:example.com".parse().unwrap();
+
+    // the line 电信 has no counterpart and "old" is kept without --prune
+    migrate(
+      &source,
+      &from,
+      &target,
+      &to,
+      &[],
+      false,
+      false,
+      Format::Plain,
+    )
+    .unwrap();
+    let records = target_records(&target).unwrap();
+    let values: Vec<&str> = records.iter().map(|r| r.value.as_str()).collect();
+    assert_eq!(
+      values,
+      vec![
+        "1.2.3.4",
+        "example.github.io",
+        "5.6.7.8",
+        "mail.example.com"
+      ]
+    );
+    let mx = records.iter().find(|r| r.r_type == "MX").unwrap();
+    assert_eq!((mx.r_line.as_str(), mx.mx), ("default", Some(10)));
+
+    migrate(
+      &source,
+      &from,
+      &target,
+      &to,
+      &[],
+      true,
+      false,
+      Format::Plain,
+    )
+    .unwrap();
+    assert_eq!(target_records(&target).unwrap().len(), 3);
+  }
+}
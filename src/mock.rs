@@ -0,0 +1,88 @@
+//! Local stand-in servers used by the unit tests.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug)]
+pub struct HttpRequest {
+  pub method: String,
+  pub path: String,
+  pub headers: Vec<(String, String)>,
+  pub body: String,
+}
+
+impl HttpRequest {
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(n, _)| n.eq_ignore_ascii_case(name))
+      .map(|(_, v)| v.as_str())
+  }
+}
+
+/// Serve one response per accepted connection, in order.
+///
+/// Returns the base url of the server and a handle yielding the received
+/// requests once every response has been sent.
+pub fn http_server(
+  responses: Vec<String>,
+) -> (String, JoinHandle<Vec<HttpRequest>>) {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", listener.local_addr().unwrap());
+  let handle = thread::spawn(move || {
+    let mut requests = Vec::new();
+    for response in responses {
+      let (stream, _) = listener.accept().unwrap();
+      let mut reader = BufReader::new(stream);
+
+      let mut line = String::new();
+      reader.read_line(&mut line).unwrap();
+      let mut parts = line.split_whitespace();
+      let method = String::from(parts.next().unwrap());
+      let path = String::from(parts.next().unwrap());
+
+      let mut headers = Vec::new();
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+          break;
+        }
+        let (name, value) = line.split_once(':').unwrap();
+        headers.push((String::from(name), String::from(value.trim())));
+      }
+
+      let request = HttpRequest {
+        method,
+        path,
+        headers,
+        body: String::new(),
+      };
+      let length = request
+        .header("Content-Length")
+        .map_or(0, |l| l.parse().unwrap());
+      let mut body = vec![0; length];
+      reader.read_exact(&mut body).unwrap();
+
+      let mut stream = reader.into_inner();
+      write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.len(),
+        response
+      )
+      .unwrap();
+
+      requests.push(HttpRequest {
+        body: String::from_utf8(body).unwrap(),
+        ..request
+      });
+    }
+    requests
+  });
+  (url, handle)
+}
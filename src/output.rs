@@ -0,0 +1,314 @@
+//! Output of the commands, as text for people or as JSON and CSV for
+//! scripts.
+
+use std::str::FromStr;
+
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::{Error, Result};
+use crate::interface::InterfaceAddr;
+use crate::provider::interface::{Record, RecordStatus};
+
+pub const FORMATS: &[&str] = &["plain", "table", "json", "csv"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+  Plain,
+  Table,
+  Json,
+  Csv,
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+    match format {
+      "plain" => Ok(Format::Plain),
+      "table" => Ok(Format::Table),
+      "json" => Ok(Format::Json),
+      "csv" => Ok(Format::Csv),
+      _ => Err(format!(
+        "Unknown output format {}, expected one of {}",
+        format,
+        FORMATS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Items printed by the commands.
+pub trait Tabular: Serialize {
+  /// Column names of the table and CSV outputs.
+  const HEADERS: &'static [&'static str];
+
+  /// Cells of the item, in the order of the headers.
+  fn row(&self) -> Vec<String>;
+
+  /// Line of the plain output.
+  fn plain(&self) -> String;
+}
+
+/// Columns are aligned by the display width of the cells, CJK characters
+/// taking two columns.
+fn table<T: Tabular>(items: &[T]) -> String {
+  let rows: Vec<Vec<String>> = items.iter().map(Tabular::row).collect();
+  let mut widths: Vec<usize> = T::HEADERS.iter().map(|h| h.width()).collect();
+  for row in &rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.width());
+    }
+  }
+
+  let line = |cells: Vec<&str>| {
+    let cells: Vec<String> = cells
+      .iter()
+      .zip(&widths)
+      .map(|(cell, width)| {
+        let pad = width.saturating_sub(cell.width());
+        format!("{}{}", cell, " ".repeat(pad))
+      })
+      .collect();
+    format!("{}\n", cells.join("  ").trim_end())
+  };
+  let mut table = line(T::HEADERS.to_vec());
+  for row in &rows {
+    table.push_str(&line(row.iter().map(String::as_str).collect()));
+  }
+  table
+}
+
+/// Items as CSV, quoted as the zone files read by import.
+fn csv<T: Tabular>(items: &[T]) -> Result<String> {
+  let mut writer = csv::Writer::from_writer(Vec::new());
+  writer.write_record(T::HEADERS)?;
+  for item in items {
+    writer.write_record(item.row())?;
+  }
+  let csv = writer
+    .into_inner()
+    .map_err(|err| Error::Reason(err.to_string()))?;
+  Ok(String::from_utf8(csv)?)
+}
+
+/// Render a list of items, as a JSON array in JSON.
+pub fn render<T: Tabular>(format: Format, items: &[T]) -> Result<String> {
+  Ok(match format {
+    Format::Plain => items.iter().map(|i| format!("{}\n", i.plain())).collect(),
+    Format::Table => table(items),
+    Format::Json => format!("{}\n", serde_json::to_string_pretty(items)?),
+    Format::Csv => csv(items)?,
+  })
+}
+
+/// Print a list of items.
+pub fn print_all<T: Tabular>(format: Format, items: &[T]) -> Result<()> {
+  print!("{}", render(format, items)?);
+  Ok(())
+}
+
+/// Print the single result of a command, as a JSON object in JSON.
+pub fn print_one<T: Tabular>(format: Format, item: &T) -> Result<()> {
+  match format {
+    Format::Json => println!("{}", serde_json::to_string_pretty(item)?),
+    _ => print_all(format, std::slice::from_ref(item))?,
+  }
+  Ok(())
+}
+
+/// Result of a command changing a record.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Outcome {
+  /// What was done: `added`, `modified`, `deleted`, `enabled`, `disabled`,
+  /// or the result of a sync: `created`, `updated` or `unchanged`.
+  pub action: &'static str,
+  pub id: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sub_domain: Option<String>,
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub record_type: Option<String>,
+  #[serde(rename = "line", skip_serializing_if = "Option::is_none")]
+  pub record_line: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub value: Option<String>,
+  /// The record listed again from the provider after an add or a modify,
+  /// or before a delete, not the response of the API to the change.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub record: Option<Record>,
+}
+
+impl Outcome {
+  pub fn deleted(id: String) -> Self {
+    Outcome {
+      action: "deleted",
+      id,
+      sub_domain: None,
+      record_type: None,
+      record_line: None,
+      value: None,
+      record: None,
+    }
+  }
+
+  pub fn status(id: String, status: RecordStatus) -> Self {
+    Outcome {
+      action: match status {
+        RecordStatus::Enabled => "enabled",
+        RecordStatus::Disabled => "disabled",
+      },
+      ..Outcome::deleted(id)
+    }
+  }
+
+  pub fn changed(
+    action: &'static str,
+    id: String,
+    sub_domain: Option<&str>,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+  ) -> Self {
+    Outcome {
+      action,
+      id,
+      sub_domain: sub_domain.map(String::from),
+      record_type: Some(String::from(record_type)),
+      record_line: Some(String::from(record_line)),
+      value: Some(String::from(value)),
+      record: None,
+    }
+  }
+
+  pub fn with_record(self, record: Option<Record>) -> Self {
+    Outcome { record, ..self }
+  }
+}
+
+impl Tabular for Outcome {
+  const HEADERS: &'static [&'static str] =
+    &["action", "id", "sub_domain", "type", "line", "value"];
+
+  fn row(&self) -> Vec<String> {
+    vec![
+      String::from(self.action),
+      self.id.clone(),
+      self.sub_domain.clone().unwrap_or_default(),
+      self.record_type.clone().unwrap_or_default(),
+      self.record_line.clone().unwrap_or_default(),
+      self.value.clone().unwrap_or_default(),
+    ]
+  }
+
+  /// The id, prefixed with the result of a sync.
+  fn plain(&self) -> String {
+    match self.action {
+      "created" | "updated" | "unchanged" => {
+        format!("{}: {}", self.action, self.id)
+      }
+      _ => self.id.clone(),
+    }
+  }
+}
+
+impl Tabular for Record {
+  const HEADERS: &'static [&'static str] = &[
+    "id",
+    "sub_domain",
+    "type",
+    "line",
+    "value",
+    "ttl",
+    "mx",
+    "weight",
+    "status",
+    "remark",
+  ];
+
+  fn row(&self) -> Vec<String> {
+    vec![
+      self.id.clone(),
+      self.sub_domain.clone(),
+      self.r_type.clone(),
+      self.r_line.clone(),
+      self.value.clone(),
+      self.ttl.map(|t| t.to_string()).unwrap_or_default(),
+      self.mx.map(|m| m.to_string()).unwrap_or_default(),
+      self.weight.map(|w| w.to_string()).unwrap_or_default(),
+      self.status.map(|s| s.to_string()).unwrap_or_default(),
+      self.remark.clone().unwrap_or_default(),
+    ]
+  }
+
+  fn plain(&self) -> String {
+    self.to_string()
+  }
+}
+
+impl Tabular for InterfaceAddr {
+  const HEADERS: &'static [&'static str] = &["name", "address", "prefix_len"];
+
+  fn row(&self) -> Vec<String> {
+    vec![
+      self.name.clone(),
+      self.address.to_string(),
+      self.prefix_len.map(|l| l.to_string()).unwrap_or_default(),
+    ]
+  }
+
+  fn plain(&self) -> String {
+    match self.prefix_len {
+      Some(len) => format!("{}: {}/{}", self.name, self.address, len),
+      None => format!("{}: {}", self.name, self.address),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn outcome(value: &str) -> Outcome {
+    Outcome::changed(
+      "added",
+      String::from("1"),
+      Some("www"),
+      "A",
+      "默认",
+      value,
+    )
+  }
+
+  #[test]
+  fn test_render() {
+    let items = vec![outcome("1.2.3.4"), outcome("a,\"b\"")];
+    assert_eq!(render(Format::Plain, &items).unwrap(), "1\n1\n");
+    assert_eq!(
+      render(Format::Csv, &items).unwrap(),
+      "action,id,sub_domain,type,line,value\n\
+       added,1,www,A,默认,1.2.3.4\n\
+       added,1,www,A,默认,\"a,\"\"b\"\"\"\n"
+    );
+    let json: serde_json::Value =
+      serde_json::from_str(&render(Format::Json, &items).unwrap()).unwrap();
+    assert_eq!(json[0]["type"], "A");
+    assert_eq!(
+      serde_json::to_value(Outcome::deleted(String::from("1"))).unwrap(),
+      serde_json::json!({ "action": "deleted", "id": "1" })
+    );
+    let record = crate::provider::memory::record("1", "www", "A", "1.2.3.4");
+    let json =
+      serde_json::to_value(outcome("1.2.3.4").with_record(Some(record)))
+        .unwrap();
+    assert_eq!(json["record"]["ttl"], 600);
+    let mut wide = outcome("1.2.3.4");
+    wide.sub_domain = Some(String::from("中文"));
+    assert_eq!(
+      render(Format::Table, &[outcome("1.2.3.4"), wide]).unwrap(),
+      "action  id  sub_domain  type  line  value\n\
+       added   1   www         A     默认  1.2.3.4\n\
+       added   1   中文        A     默认  1.2.3.4\n"
+    );
+    assert!("yaml".parse::<Format>().is_err());
+  }
+}
@@ -0,0 +1,468 @@
+//! Records of the configuration file managed as a whole: the records are
+//! compared with the ones of the provider and the differences are shown as
+//! a plan of adds, changes and deletes, which `apply` then executes.
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::interface::interface_or_value;
+use crate::output::{print_all, Format, Tabular};
+use crate::provider::interface::{
+  list_all, DnsProvider, Record, RecordFilter, RecordSpec,
+};
+use crate::sync::record_match;
+
+/// A record as it should be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desired {
+  pub sub_domain: String,
+  pub record_type: String,
+  pub record_line: String,
+  /// Unknown when it could not be read, e.g. from an interface without
+  /// address. The records of the sub-domain, type and line are then left as
+  /// they are.
+  pub value: Option<String>,
+  pub spec: RecordSpec,
+}
+
+impl Desired {
+  fn matches(&self, record: &Record) -> bool {
+    record_match(
+      record,
+      &self.sub_domain,
+      &self.record_type,
+      &self.record_line,
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+  Add,
+  Change,
+  Delete,
+}
+
+impl Action {
+  fn name(self) -> &'static str {
+    match self {
+      Action::Add => "add",
+      Action::Change => "change",
+      Action::Delete => "delete",
+    }
+  }
+
+  fn sign(self) -> char {
+    match self {
+      Action::Add => '+',
+      Action::Change => '~',
+      Action::Delete => '-',
+    }
+  }
+}
+
+/// A step of the plan.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Step {
+  pub action: Action,
+  pub domain: String,
+  /// Record changed or deleted.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub id: Option<String>,
+  pub sub_domain: String,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  #[serde(rename = "line")]
+  pub record_line: String,
+  /// Value of the record changed or deleted.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub old_value: Option<String>,
+  /// Value of the record added or changed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub value: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ttl: Option<u32>,
+  #[serde(skip)]
+  pub spec: RecordSpec,
+}
+
+impl Step {
+  fn add(domain: &str, desired: &Desired) -> Self {
+    Step {
+      action: Action::Add,
+      domain: String::from(domain),
+      id: None,
+      sub_domain: desired.sub_domain.clone(),
+      record_type: desired.record_type.clone(),
+      record_line: desired.record_line.clone(),
+      old_value: None,
+      value: desired.value.clone(),
+      ttl: desired.spec.ttl,
+      spec: desired.spec.clone(),
+    }
+  }
+
+  fn change(domain: &str, record: &Record, desired: &Desired) -> Self {
+    Step {
+      action: Action::Change,
+      id: Some(record.id.clone()),
+      old_value: Some(record.value.clone()),
+      ..Step::add(domain, desired)
+    }
+  }
+
+  fn delete(domain: &str, record: &Record) -> Self {
+    Step {
+      action: Action::Delete,
+      domain: String::from(domain),
+      id: Some(record.id.clone()),
+      sub_domain: record.sub_domain.clone(),
+      record_type: record.r_type.clone(),
+      record_line: record.r_line.clone(),
+      old_value: Some(record.value.clone()),
+      value: None,
+      ttl: None,
+      spec: RecordSpec::default(),
+    }
+  }
+
+  fn name(&self) -> String {
+    if self.sub_domain == "@" {
+      self.domain.clone()
+    } else {
+      format!("{}.{}", self.sub_domain, self.domain)
+    }
+  }
+
+  fn run(&self, provider: &dyn DnsProvider) -> Result<()> {
+    let id = self.id.as_deref().unwrap_or_default();
+    let value = self.value.as_deref().unwrap_or_default();
+    match self.action {
+      Action::Add => provider
+        .add_record(
+          &self.sub_domain,
+          &self.record_type,
+          &self.record_line,
+          value,
+          &self.spec,
+        )
+        .map(drop),
+      Action::Change => provider
+        .modify_record(
+          id,
+          Some(&self.sub_domain),
+          &self.record_type,
+          &self.record_line,
+          value,
+          &self.spec,
+        )
+        .map(drop),
+      Action::Delete => provider.delete_record(id),
+    }
+  }
+}
+
+impl Tabular for Step {
+  const HEADERS: &'static [&'static str] = &[
+    "action",
+    "domain",
+    "id",
+    "sub_domain",
+    "type",
+    "line",
+    "old_value",
+    "value",
+    "ttl",
+  ];
+
+  fn row(&self) -> Vec<String> {
+    vec![
+      String::from(self.action.name()),
+      self.domain.clone(),
+      self.id.clone().unwrap_or_default(),
+      self.sub_domain.clone(),
+      self.record_type.clone(),
+      self.record_line.clone(),
+      self.old_value.clone().unwrap_or_default(),
+      self.value.clone().unwrap_or_default(),
+      self.ttl.map(|t| t.to_string()).unwrap_or_default(),
+    ]
+  }
+
+  /// A line of diff, e.g. `~ www.example.com A 默认 1.2.3.4 -> 2.3.4.5`.
+  fn plain(&self) -> String {
+    let value = match (&self.old_value, &self.value) {
+      (Some(old), Some(new)) => format!("{} -> {}", old, new),
+      (Some(value), None) | (None, Some(value)) => value.clone(),
+      (None, None) => String::new(),
+    };
+    let ttl = self.ttl.map(|t| format!(" ttl {}", t)).unwrap_or_default();
+    format!(
+      "{} {} {} {} {}{}",
+      self.action.sign(),
+      self.name(),
+      self.record_type,
+      self.record_line,
+      value,
+      ttl
+    )
+  }
+}
+
+/// Steps bringing the records of a domain to the desired ones.
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+  pub steps: Vec<Step>,
+  /// Number of unmanaged records kept, as `prune` was not set.
+  pub unmanaged: usize,
+}
+
+/// Whether the record belongs to the zone itself and is never deleted.
+pub fn is_zone_record(sub_domain: &str, record_type: &str) -> bool {
+  sub_domain == "@"
+    && (record_type.eq_ignore_ascii_case("SOA")
+      || record_type.eq_ignore_ascii_case("NS"))
+}
+
+/// Plan the steps bringing the `existing` records of `domain` to the
+/// `desired` ones.
+///
+/// Records with the sub-domain, type and line of a desired record are
+/// managed: they are changed to hold the desired values and the surplus
+/// is deleted. Other records are unmanaged and only deleted with `prune`,
+/// except the SOA and NS records of the apex.
+pub fn plan(
+  domain: &str,
+  existing: &[Record],
+  desired: &[Desired],
+  prune: bool,
+) -> Plan {
+  let mut result = Plan::default();
+  let mut used = vec![false; existing.len()];
+
+  // records already holding a desired value first, so that they are kept
+  let mut pending = Vec::new();
+  for desired in desired {
+    let value = match &desired.value {
+      Some(value) => value,
+      None => continue,
+    };
+    let found = existing.iter().enumerate().find(|(i, record)| {
+      !used[*i] && desired.matches(record) && record.value == *value
+    });
+    match found {
+      Some((i, record)) => {
+        used[i] = true;
+        if !desired.spec.satisfied_by(record) {
+          result.steps.push(Step::change(domain, record, desired));
+        }
+      }
+      None => pending.push(desired),
+    }
+  }
+
+  for desired in pending {
+    let found = existing
+      .iter()
+      .enumerate()
+      .find(|(i, record)| !used[*i] && desired.matches(record));
+    match found {
+      Some((i, record)) => {
+        used[i] = true;
+        result.steps.push(Step::change(domain, record, desired));
+      }
+      None => result.steps.push(Step::add(domain, desired)),
+    }
+  }
+
+  for (record, _) in existing.iter().zip(&used).filter(|(_, used)| !**used) {
+    let mut managers = desired.iter().filter(|d| d.matches(record)).peekable();
+    if managers.peek().is_some() {
+      if managers.all(|d| d.value.is_some()) {
+        result.steps.push(Step::delete(domain, record));
+      }
+    } else if is_zone_record(&record.sub_domain, &record.r_type) {
+      continue;
+    } else if prune {
+      result.steps.push(Step::delete(domain, record));
+    } else {
+      result.unmanaged += 1;
+    }
+  }
+
+  result
+}
+
+/// Run the steps, reporting the failed ones and going on with the others.
+///
+/// Returns the number of failed steps.
+pub fn apply(provider: &dyn DnsProvider, steps: &[Step]) -> usize {
+  let mut failed = 0;
+  for step in steps {
+    if let Err(err) = step.run(provider) {
+      failed += 1;
+      eprintln!("{}: {}", step.plain(), err);
+    }
+  }
+  failed
+}
+
+/// Plan the records of every domain of the configuration, and run the plan
+/// when `run` is set.
+///
+/// Failures are reported and the remaining domains are still processed.
+pub fn plan_config(
+  config: &Config,
+  prune: bool,
+  run: bool,
+  dry_run: bool,
+  output: Format,
+) -> Result<()> {
+  let mut failed = 0;
+  let mut steps = Vec::new();
+  for (provider, domain) in config.build(dry_run)? {
+    let mut desired = Vec::new();
+    for record in &domain.records {
+      let value = interface_or_value(
+        record.interface.clone(),
+        record.ip_source.clone(),
+        record.value.clone(),
+        &record.record_type,
+      );
+      let value = match value {
+        Ok(value) => Some(value),
+        Err(err) => {
+          failed += 1;
+          eprintln!(
+            "{}.{} {}: {}, left as is",
+            record.sub_domain, domain.name, record.record_type, err
+          );
+          None
+        }
+      };
+      desired.push(Desired {
+        sub_domain: record.sub_domain.clone(),
+        record_type: record.record_type.clone(),
+        record_line: record.record_line.clone(),
+        value,
+        spec: record.spec(),
+      });
+    }
+
+    let existing = match list_all(provider.as_ref(), &RecordFilter::default()) {
+      Ok(page) => page.records,
+      Err(err) => {
+        failed += 1;
+        eprintln!("{}: {}", domain.name, err);
+        continue;
+      }
+    };
+    let plan = plan(&domain.name, &existing, &desired, prune);
+    if plan.unmanaged > 0 {
+      eprintln!(
+        "{}: {} unmanaged record(s) kept, use --prune to delete them",
+        domain.name, plan.unmanaged
+      );
+    }
+    if run {
+      failed += apply(provider.as_ref(), &plan.steps);
+    }
+    steps.extend(plan.steps);
+  }
+
+  if steps.is_empty() {
+    eprintln!("No changes");
+  } else {
+    print_all(output, &steps)?;
+  }
+  if failed == 0 {
+    Ok(())
+  } else {
+    Err(Error::Reason(format!("{} record(s) failed", failed)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::memory::{self, record};
+
+  fn desired(sub_domain: &str, value: Option<&str>) -> Desired {
+    Desired {
+      sub_domain: String::from(sub_domain),
+      record_type: String::from("A"),
+      record_line: String::from("0"),
+      value: value.map(String::from),
+      spec: RecordSpec::default(),
+    }
+  }
+
+  #[test]
+  fn test_plan() {
+    let existing = vec![
+      record("1", "@", "NS", "ns1.example.net."),
+      record("2", "www", "A", "1.2.3.4"),
+      record("3", "www", "A", "2.3.4.5"),
+      record("4", "home", "A", "3.4.5.6"),
+      record("5", "nas", "A", "4.5.6.7"),
+      record("6", "old", "A", "5.6.7.8"),
+    ];
+    let mut desired = vec![
+      desired("www", Some("2.3.4.5")),
+      desired("home", Some("6.7.8.9")),
+      desired("nas", None),
+      desired("new", Some("7.8.9.0")),
+    ];
+    desired[0].spec.ttl = Some(60);
+
+    let plan = super::plan("example.com", &existing, &desired, false);
+    let steps: Vec<String> = plan.steps.iter().map(Step::plain).collect();
+    assert_eq!(
+      steps,
+      vec![
+        "~ www.example.com A 0 2.3.4.5 -> 2.3.4.5 ttl 60",
+        "~ home.example.com A 0 3.4.5.6 -> 6.7.8.9",
+        "+ new.example.com A 0 7.8.9.0",
+        "- www.example.com A 默认 1.2.3.4",
+      ]
+    );
+    assert_eq!(plan.unmanaged, 1);
+
+    let pruned = super::plan("example.com", &existing, &desired, true);
+    assert_eq!(pruned.steps.len(), 5);
+    assert_eq!(pruned.steps[4].id.as_deref(), Some("6"));
+    assert_eq!(pruned.unmanaged, 0);
+
+    let provider = memory::Provider::with_records(existing);
+    assert_eq!(apply(&provider, &pruned.steps), 0);
+    let records = list_all(&provider, &RecordFilter::default()).unwrap();
+    let desired_again =
+      super::plan("example.com", &records.records, &desired, true);
+    assert_eq!(desired_again, Plan::default());
+  }
+
+  #[test]
+  fn test_plan_lines() {
+    let telecom = Record {
+      r_line: String::from("电信"),
+      line_id: Some(String::from("10=0")),
+      ..record("2", "www", "A", "2.3.4.5")
+    };
+    let existing = vec![record("1", "www", "A", "1.2.3.4"), telecom];
+
+    // records of other lines are unmanaged, the line id matching its line
+    let default = vec![desired("www", Some("1.2.3.4"))];
+    let plan = super::plan("example.com", &existing, &default, false);
+    assert_eq!(plan.steps, vec![]);
+    assert_eq!(plan.unmanaged, 1);
+
+    let mut telecom = vec![desired("www", Some("3.4.5.6"))];
+    telecom[0].record_line = String::from("电信");
+    let plan = super::plan("example.com", &existing, &telecom, false);
+    let steps: Vec<String> = plan.steps.iter().map(Step::plain).collect();
+    assert_eq!(steps, vec!["~ www.example.com A 电信 2.3.4.5 -> 3.4.5.6"]);
+    assert_eq!(plan.unmanaged, 1);
+  }
+}
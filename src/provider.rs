@@ -3,19 +3,57 @@ pub mod interface;
 #[cfg(feature = "dnspod")]
 mod dnspod;
 
+#[cfg(feature = "cloudflare")]
+mod cloudflare;
+
+#[cfg(feature = "alidns")]
+mod alidns;
+
+#[cfg(feature = "rfc2136")]
+mod rfc2136;
+
+mod dry_run;
+
+#[cfg(test)]
+pub mod memory;
+
 use self::interface::{DnsProvider, DnsProviderBuild};
 use crate::error::{Error, Result};
 
-type BoxDnsProvider = Box<dyn DnsProvider>;
+pub type BoxDnsProvider = Box<dyn DnsProvider>;
 
+/// Build the provider, logging the changes instead of making them when
+/// `dry_run` is set.
 pub fn build_dns_provider(
   provider: &str,
   key: String,
   domain: String,
+  dry_run: bool,
+) -> Result<BoxDnsProvider> {
+  let provider = build_provider(provider, key, domain)?;
+  if dry_run {
+    Ok(Box::new(dry_run::Provider::new(provider)))
+  } else {
+    Ok(provider)
+  }
+}
+
+fn build_provider(
+  provider: &str,
+  key: String,
+  domain: String,
 ) -> Result<BoxDnsProvider> {
   match provider as &str {
     #[cfg(feature = "dnspod")]
     "dnspod" => Ok(Box::new(dnspod::Provider::build_provider(key, domain)?)),
+    #[cfg(feature = "cloudflare")]
+    "cloudflare" => {
+      Ok(Box::new(cloudflare::Provider::build_provider(key, domain)?))
+    }
+    #[cfg(feature = "alidns")]
+    "alidns" => Ok(Box::new(alidns::Provider::build_provider(key, domain)?)),
+    #[cfg(feature = "rfc2136")]
+    "rfc2136" => Ok(Box::new(rfc2136::Provider::build_provider(key, domain)?)),
     _ => Err(Error::Reason(String::from(provider))),
   }
 }
@@ -0,0 +1,651 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::Value;
+use sha1::Sha1;
+
+use super::interface::*;
+use crate::error::{Error, Result};
+
+mod intra_common {
+  pub const CAO_FORM_DOMAIN: &str = "DomainName";
+  pub const CAO_FORM_SDOMAIN: &str = "RR";
+  pub const CAO_FORM_SDOMAIN_KEYWORD: &str = "RRKeyWord";
+  pub const CAO_FORM_RID: &str = "RecordId";
+  pub const CAO_FORM_RTYPE: &str = "Type";
+  pub const CAO_FORM_VALUE: &str = "Value";
+  pub const CAO_FORM_RLINE: &str = "Line";
+  /// Code of the default line.
+  pub const LINE_DEFAULT: &str = "default";
+  /// Id of the default line, mapped to its code as for DNSPod.
+  pub const LINE_ID_DEFAULT: &str = "0";
+  /// Fields of the spec taken by the provider.
+  pub const SPEC_FIELDS: &[&str] = &["ttl", "mx", "status"];
+  pub const CAO_FORM_TTL: &str = "TTL";
+  pub const CAO_FORM_PRIORITY: &str = "Priority";
+  pub const CAO_FORM_STATUS: &str = "Status";
+  pub const CAO_FORM_PAGE: &str = "PageNumber";
+  pub const CAO_FORM_PAGE_SIZE: &str = "PageSize";
+
+  pub const ALIDNS_API: &str = "https://alidns.aliyuncs.com/";
+  pub const ALIDNS_API_VERSION: &str = "2015-01-09";
+  pub const ALIDNS_SIGNATURE_METHOD: &str = "HMAC-SHA1";
+  pub const ALIDNS_SIGNATURE_VERSION: &str = "1.0";
+  /// Page size used when listing records.
+  pub const ALIDNS_PAGE: usize = 100;
+}
+
+/// Characters kept as is by the percent encoding of the RPC signature.
+const RPC_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'_')
+  .remove(b'.')
+  .remove(b'~');
+
+fn percent_encode(s: &str) -> String {
+  utf8_percent_encode(s, RPC_ENCODE).to_string()
+}
+
+/// Names of lines on DNSPod and their AliDNS line codes.
+const LINES: &[(&str, &str)] = &[
+  (COMMON_LINE_DEFAULT, intra_common::LINE_DEFAULT),
+  ("电信", "telecom"),
+  ("联通", "unicom"),
+  ("移动", "mobile"),
+  ("教育网", "edu"),
+  ("境外", "oversea"),
+  ("搜索引擎", "search"),
+  ("百度", "baidu"),
+  ("谷歌", "google"),
+  ("必应", "biying"),
+];
+
+/// Names of URL forwarding types on DNSPod and their AliDNS types.
+const URL_TYPES: &[(&str, &str)] =
+  &[("显性URL", "REDIRECT_URL"), ("隐性URL", "FORWARD_URL")];
+
+/// Map DNSPod record lines to AliDNS line codes.
+///
+/// Unknown lines are passed through so AliDNS line codes can be used
+/// directly.
+fn record_line_map(record_line: &str) -> &str {
+  if record_line == intra_common::LINE_ID_DEFAULT {
+    return intra_common::LINE_DEFAULT;
+  }
+  LINES
+    .iter()
+    .find(|(name, _)| *name == record_line)
+    .map_or(record_line, |(_, code)| code)
+}
+
+/// Sign the canonicalized query with the RPC signature version 1.0.
+fn rpc_signature(
+  method: &str,
+  params: &[(String, String)],
+  secret: &str,
+) -> Result<String> {
+  let mut params: Vec<(String, String)> = params
+    .iter()
+    .map(|(k, v)| (percent_encode(k), percent_encode(v)))
+    .collect();
+  params.sort();
+  let canonicalized = params
+    .iter()
+    .map(|(k, v)| format!("{}={}", k, v))
+    .collect::<Vec<_>>()
+    .join("&");
+  let string_to_sign = format!(
+    "{}&{}&{}",
+    method,
+    percent_encode("/"),
+    percent_encode(&canonicalized)
+  );
+  tracing::debug!("STRING_TO_SIGN: {}", string_to_sign);
+
+  let mut mac =
+    Hmac::<Sha1>::new_from_slice(format!("{}&", secret).as_bytes())?;
+  mac.update(string_to_sign.as_bytes());
+  let code = mac.finalize().into_bytes();
+  Ok(base64::engine::general_purpose::STANDARD.encode(code))
+}
+
+fn signature_nonce() -> String {
+  static COUNTER: AtomicU32 = AtomicU32::new(0);
+  format!(
+    "{}-{}-{}",
+    chrono::offset::Utc::now()
+      .timestamp_nanos_opt()
+      .unwrap_or_default(),
+    std::process::id(),
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+  )
+}
+
+fn record_parse(record: &Value) -> Option<Record> {
+  let id = String::from(record.get("RecordId")?.as_str()?);
+  let sub_domain = String::from(record.get("RR")?.as_str()?);
+  let value = String::from(record.get("Value")?.as_str()?);
+  let r_type = String::from(record.get("Type")?.as_str()?);
+  let r_line = String::from(record.get("Line")?.as_str()?);
+  let line_id = Some(intra_common::LINE_ID_DEFAULT)
+    .filter(|_| r_line == intra_common::LINE_DEFAULT)
+    .map(String::from);
+  let ttl = record.get("TTL").and_then(Value::as_u64).map(|t| t as u32);
+  let mx = record
+    .get("Priority")
+    .and_then(Value::as_u64)
+    .map(|p| p as u16);
+  let weight = record
+    .get("Weight")
+    .and_then(Value::as_u64)
+    .map(|w| w as u32);
+  let status = record
+    .get("Status")
+    .and_then(Value::as_str)
+    .and_then(|status| status.parse().ok());
+  let remark = record
+    .get("Remark")
+    .and_then(Value::as_str)
+    .filter(|remark| !remark.is_empty())
+    .map(String::from);
+  Some(Record {
+    id,
+    sub_domain,
+    value,
+    r_type,
+    r_line,
+    line_id,
+    ttl,
+    mx,
+    weight,
+    status,
+    remark,
+  })
+}
+
+/// Parameters of `AddDomainRecord` and `UpdateDomainRecord` for the spec,
+/// which only take the TTL and the MX priority, the status being set by
+/// `SetDomainRecordStatus`.
+fn spec_params(spec: &RecordSpec) -> Vec<(&'static str, String)> {
+  spec.warn_unsupported("alidns", intra_common::SPEC_FIELDS);
+  let mut params = Vec::new();
+  if let Some(ttl) = spec.ttl {
+    params.push((intra_common::CAO_FORM_TTL, ttl.to_string()));
+  }
+  if let Some(mx) = spec.mx {
+    params.push((intra_common::CAO_FORM_PRIORITY, mx.to_string()));
+  }
+  params
+}
+
+pub struct Provider {
+  /// AccessKey id
+  id: String,
+  /// AccessKey secret
+  key: String,
+  domain: String,
+  api: String,
+  client: reqwest::blocking::Client,
+}
+
+impl Provider {
+  /// Build a provider against the API at `api`.
+  pub fn with_api(api: &str, token: String, domain: String) -> Result<Self> {
+    let mut split = token.split(',');
+    let id = split.next().map(str::trim).unwrap_or_default();
+    let key = split.next().map(str::trim).ok_or_else(|| {
+      Error::Reason(String::from(
+        "AliDNS key should be ACCESS_KEY_ID,ACCESS_KEY_SECRET",
+      ))
+    })?;
+    Ok(Provider {
+      id: String::from(id),
+      key: String::from(key),
+      domain,
+      api: String::from(api),
+      client: reqwest::blocking::Client::new(),
+    })
+  }
+
+  fn request(&self, action: &str, payload: &[(&str, &str)]) -> Result<Value> {
+    let timestamp = chrono::offset::Utc::now()
+      .format("%Y-%m-%dT%H:%M:%SZ")
+      .to_string();
+    let nonce = signature_nonce();
+    let mut params: Vec<(String, String)> = vec![
+      ("Action", action),
+      ("Format", "JSON"),
+      ("Version", intra_common::ALIDNS_API_VERSION),
+      ("AccessKeyId", self.id.as_str()),
+      ("SignatureMethod", intra_common::ALIDNS_SIGNATURE_METHOD),
+      ("SignatureVersion", intra_common::ALIDNS_SIGNATURE_VERSION),
+      ("SignatureNonce", nonce.as_str()),
+      ("Timestamp", timestamp.as_str()),
+    ]
+    .into_iter()
+    .chain(payload.iter().copied())
+    .map(|(k, v)| (String::from(k), String::from(v)))
+    .collect();
+    let signature = rpc_signature("GET", &params, &self.key)?;
+    params.push((String::from("Signature"), signature));
+
+    tracing::debug!("URL: {:?}", self.api);
+    tracing::debug!("QUERY: {:?}", &params);
+
+    let result: Value = self
+      .client
+      .get(&self.api)
+      .header("User-Agent", CAO_USER_AGENT)
+      .query(&params)
+      .send()?
+      .json()?;
+
+    //
+    // Result process
+    #[cfg(debug_assertions)]
+    eprintln!("{}", serde_json::to_string_pretty(&result)?);
+
+    if result.get("Code").is_some() {
+      Err(Error::http_failed(format!("Request failed {:?}", &result)))
+    } else {
+      Ok(result)
+    }
+  }
+
+  fn result_id(result: &Value) -> Result<String> {
+    result
+      .get(intra_common::CAO_FORM_RID)
+      .and_then(Value::as_str)
+      .map(String::from)
+      .ok_or_else(|| {
+        Error::http_failed(format!("Record id parse failed {:?}", result))
+      })
+  }
+}
+
+impl DnsProviderBuild for Provider {
+  fn build_provider(token: String, domain: String) -> Result<Self> {
+    Provider::with_api(intra_common::ALIDNS_API, token, domain)
+  }
+}
+
+impl DnsProvider for Provider {
+  fn add_record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let params = spec_params(spec);
+    let mut payload = vec![
+      (intra_common::CAO_FORM_DOMAIN, self.domain.as_str()),
+      (intra_common::CAO_FORM_SDOMAIN, sub_domain),
+      (intra_common::CAO_FORM_RTYPE, record_type),
+      (intra_common::CAO_FORM_VALUE, value),
+      (intra_common::CAO_FORM_RLINE, record_line_map(record_line)),
+    ];
+    payload.extend(params.iter().map(|(k, v)| (*k, v.as_str())));
+    let result = self.request("AddDomainRecord", &payload)?;
+    let id = Self::result_id(&result)?;
+    // records are added enabled
+    if spec.status == Some(RecordStatus::Disabled) {
+      self.set_record_status(&id, RecordStatus::Disabled)?;
+    }
+    Ok(id)
+  }
+
+  /// Records from `offset`, counted in the records of the sub-domain
+  /// keyword which is a fuzzy match.
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    let filter = RecordFilter::sub_domain(sub_domain);
+    let mut page = self.list_record_page(
+      offset.unwrap_or(0).max(0) as usize,
+      length.map_or(usize::MAX, |l| l.max(0) as usize),
+      &filter,
+    )?;
+    page.records.retain(|record| filter.matches(record));
+    Ok(page.records)
+  }
+
+  /// A page of the records of the sub-domain keyword, which is a fuzzy
+  /// match: the offset and the total count are those of the records of the
+  /// keyword, the records of other sub-domains being left to the filter.
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
+    let page_size = intra_common::ALIDNS_PAGE.to_string();
+
+    // AliDNS pages by number, fetch the pages covering the range.
+    let mut page = offset / intra_common::ALIDNS_PAGE + 1;
+    let mut skip = offset % intra_common::ALIDNS_PAGE;
+    let mut records = Vec::new();
+    let mut total = None;
+    while records.len() < length {
+      let page_str = page.to_string();
+      let mut payload = vec![
+        (intra_common::CAO_FORM_DOMAIN, self.domain.as_str()),
+        (intra_common::CAO_FORM_PAGE, page_str.as_str()),
+        (intra_common::CAO_FORM_PAGE_SIZE, page_size.as_str()),
+      ];
+      if let Some(sub_domain) = &filter.sub_domain {
+        payload.push((intra_common::CAO_FORM_SDOMAIN_KEYWORD, sub_domain));
+      }
+      let result = self.request("DescribeDomainRecords", &payload)?;
+      let list = match result.get("DomainRecords").and_then(|r| r.get("Record"))
+      {
+        Some(Value::Array(list)) => list,
+        _ => {
+          return Err(Error::http_failed(format!(
+            "Failed to parse result {:?}",
+            &result
+          )))
+        }
+      };
+      total = result
+        .get("TotalCount")
+        .and_then(Value::as_u64)
+        .map(|total| total as usize);
+
+      let count = list.len();
+      let remain = length - records.len();
+      records
+        .extend(list.iter().skip(skip).take(remain).filter_map(record_parse));
+      if count < intra_common::ALIDNS_PAGE
+        || total.is_some_and(|total| page * intra_common::ALIDNS_PAGE >= total)
+      {
+        break;
+      }
+      page += 1;
+      skip = 0;
+    }
+
+    Ok(RecordPage { records, total })
+  }
+
+  fn modify_record(
+    &self,
+    record_id: &str,
+    sub_domain: Option<&str>,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    // RR is required by UpdateDomainRecord, keep the current one.
+    let current;
+    let sub_domain = match sub_domain {
+      Some(sub_domain) => sub_domain,
+      None => {
+        let info = self.request(
+          "DescribeDomainRecordInfo",
+          &[(intra_common::CAO_FORM_RID, record_id)],
+        )?;
+        current = record_parse(&info).ok_or_else(|| {
+          Error::http_failed(format!("Failed to parse result {:?}", &info))
+        })?;
+        &current.sub_domain
+      }
+    };
+
+    let params = spec_params(spec);
+    let mut payload = vec![
+      (intra_common::CAO_FORM_RID, record_id),
+      (intra_common::CAO_FORM_SDOMAIN, sub_domain),
+      (intra_common::CAO_FORM_RTYPE, record_type),
+      (intra_common::CAO_FORM_VALUE, value),
+      (intra_common::CAO_FORM_RLINE, record_line_map(record_line)),
+    ];
+    payload.extend(params.iter().map(|(k, v)| (*k, v.as_str())));
+    let result = self.request("UpdateDomainRecord", &payload)?;
+    let id = Self::result_id(&result)?;
+    if let Some(status) = spec.status {
+      self.set_record_status(&id, status)?;
+    }
+    Ok(id)
+  }
+
+  fn delete_record(&self, id: &str) -> Result<()> {
+    self.request("DeleteDomainRecord", &[(intra_common::CAO_FORM_RID, id)])?;
+    Ok(())
+  }
+
+  fn set_record_status(&self, id: &str, status: RecordStatus) -> Result<()> {
+    let status = match status {
+      RecordStatus::Enabled => "Enable",
+      RecordStatus::Disabled => "Disable",
+    };
+    self.request(
+      "SetDomainRecordStatus",
+      &[
+        (intra_common::CAO_FORM_RID, id),
+        (intra_common::CAO_FORM_STATUS, status),
+      ],
+    )?;
+    Ok(())
+  }
+
+  fn supports_status(&self) -> bool {
+    true
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    intra_common::SPEC_FIELDS
+  }
+
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    LINES
+      .iter()
+      .find(|(_, code)| *code == record_line)
+      .map(|(name, _)| String::from(*name))
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    LINES
+      .iter()
+      .find(|(name, _)| *name == common_line)
+      .map(|(_, code)| String::from(*code))
+  }
+
+  fn common_type(&self, record_type: &str) -> Option<String> {
+    let record_type = record_type.to_uppercase();
+    let name = URL_TYPES
+      .iter()
+      .find(|(_, r_type)| *r_type == record_type)
+      .map_or(record_type.as_str(), |(name, _)| name);
+    Some(String::from(name))
+  }
+
+  fn provider_type(&self, common_type: &str) -> Option<String> {
+    let common_type = common_type.to_uppercase();
+    let r_type = URL_TYPES
+      .iter()
+      .find(|(name, _)| *name == common_type)
+      .map_or(common_type.as_str(), |(_, r_type)| r_type);
+    Some(String::from(r_type))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mock::http_server;
+  use serde_json::json;
+
+  #[test]
+  fn test_alidns_signature() {
+    // Example of the AliDNS signature documentation.
+    let params: Vec<(String, String)> = vec![
+      ("Format", "XML"),
+      ("AccessKeyId", "testid"),
+      ("Action", "DescribeDomainRecords"),
+      ("SignatureMethod", "HMAC-SHA1"),
+      ("DomainName", "example.com"),
+      ("SignatureNonce", "f59ed6a9-83fc-473b-9cc6-99c95df3856e"),
+      ("SignatureVersion", "1.0"),
+      ("Version", "2015-01-09"),
+      ("Timestamp", "2016-03-24T16:41:54Z"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (String::from(k), String::from(v)))
+    .collect();
+    assert_eq!(
+      rpc_signature("GET", &params, "testsecret").unwrap(),
+      "uRpHwaSEt3J+6KQD//svCh/x+pI="
+    );
+  }
+
+  #[test]
+  fn test_alidns_translation() {
+    let provider = Provider::with_api(
+      "http://127.0.0.1:1",
+      String::from("id,key"),
+      String::from("example.com"),
+    )
+    .unwrap();
+
+    assert_eq!(provider.common_line("unicom").as_deref(), Some("联通"));
+    assert_eq!(provider.provider_line("默认").as_deref(), Some("default"));
+    assert_eq!(provider.provider_line("教育网境外"), None);
+    assert_eq!(
+      provider.provider_type("显性URL").as_deref(),
+      Some("REDIRECT_URL")
+    );
+    assert_eq!(
+      provider.common_type("forward_url").as_deref(),
+      Some("隐性URL")
+    );
+    assert!(provider.supports_status());
+  }
+
+  #[test]
+  fn test_alidns_record_actions() {
+    let (url, server) = http_server(vec![
+      json!({ "RecordId": "9999985" }).to_string(),
+      json!({
+        "TotalCount": 2,
+        "DomainRecords": { "Record": [
+          {
+            "RecordId": "9999985",
+            "RR": "www",
+            "Type": "A",
+            "Value": "1.2.3.4",
+            "Line": "default",
+            "TTL": 600,
+            "Status": "ENABLE",
+          },
+          {
+            "RecordId": "9999986",
+            "RR": "www2",
+            "Type": "A",
+            "Value": "1.2.3.4",
+            "Line": "telecom",
+            "TTL": 600,
+          },
+        ]},
+      })
+      .to_string(),
+      json!({ "Code": "DomainRecordDuplicate", "Message": "duplicate" })
+        .to_string(),
+      json!({ "RecordId": "9999985", "Status": "Disable" }).to_string(),
+    ]);
+
+    let provider = Provider::with_api(
+      &format!("{}/", url),
+      String::from("testid,testsecret"),
+      String::from("example.com"),
+    )
+    .unwrap();
+
+    let spec = RecordSpec {
+      ttl: Some(600),
+      ..RecordSpec::default()
+    };
+    let id = provider
+      .add_record("www", "A", "默认", "1.2.3.4", &spec)
+      .unwrap();
+    assert_eq!(id, "9999985");
+    let records = provider.list_record(None, None, Some("www")).unwrap();
+    assert_eq!(
+      records,
+      vec![Record {
+        id: String::from("9999985"),
+        sub_domain: String::from("www"),
+        value: String::from("1.2.3.4"),
+        r_type: String::from("A"),
+        r_line: String::from("default"),
+        line_id: Some(String::from("0")),
+        ttl: Some(600),
+        mx: None,
+        weight: None,
+        status: Some(RecordStatus::Enabled),
+        remark: None,
+      }]
+    );
+    assert!(provider
+      .modify_record(&id, Some("www"), "A", "电信", "1.2.3.4", &spec)
+      .is_err());
+    provider
+      .set_record_status(&id, RecordStatus::Disabled)
+      .unwrap();
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].path.contains("Action=AddDomainRecord"));
+    assert!(requests[0].path.contains("Line=default"));
+    assert!(requests[0].path.contains("TTL=600"));
+    assert!(requests[0].path.contains("Signature="));
+    assert!(requests[1].path.contains("RRKeyWord=www"));
+    assert!(requests[2].path.contains("Line=telecom"));
+    assert!(requests[3].path.contains("Action=SetDomainRecordStatus"));
+    assert!(requests[3].path.contains("Status=Disable"));
+  }
+
+  #[test]
+  fn test_alidns_list_pages() {
+    // the keyword www matches the records of www2 too
+    let page = |first: usize, count: usize| {
+      let records: Vec<_> = (first..first + count)
+        .map(|i| {
+          json!({
+            "RecordId": i.to_string(),
+            "RR": if i % 2 == 0 { "www" } else { "www2" },
+            "Type": "A",
+            "Value": "1.2.3.4",
+            "Line": "default",
+          })
+        })
+        .collect();
+      json!({ "TotalCount": 150, "DomainRecords": { "Record": records } })
+        .to_string()
+    };
+    let (url, server) = http_server(vec![page(0, 100), page(100, 50)]);
+    let provider = Provider::with_api(
+      &format!("{}/", url),
+      String::from("testid,testsecret"),
+      String::from("example.com"),
+    )
+    .unwrap();
+
+    let all = list_all(&provider, &RecordFilter::sub_domain(Some("www")))
+      .unwrap()
+      .records;
+    assert_eq!(all.len(), 75);
+    assert!(all.iter().all(|r| r.sub_domain == "www"));
+    assert_eq!(all[74].id, "148");
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].path.contains("PageNumber=1"));
+    assert!(requests[1].path.contains("PageNumber=2"));
+  }
+}
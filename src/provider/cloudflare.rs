@@ -0,0 +1,499 @@
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::interface::*;
+use crate::error::{Error, Result};
+
+mod intra_common {
+  pub const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4";
+  /// Page size used when listing records.
+  pub const CLOUDFLARE_PAGE: usize = 100;
+  /// TTL value meaning "automatic".
+  pub const CLOUDFLARE_TTL_AUTO: u32 = 1;
+
+  /// Record line of proxied records.
+  pub const LINE_PROXIED: &str = "proxied";
+  /// Record line of DNS only records.
+  pub const LINE_DEFAULT: &str = "default";
+  /// Id of the line of DNS only records, any line other than the proxied
+  /// one giving DNS only records.
+  pub const LINE_ID_DEFAULT: &str = "0";
+
+  /// Fields of the spec taken by the provider.
+  pub const SPEC_FIELDS: &[&str] = &["ttl", "mx", "remark"];
+  /// Types of records taken by the provider.
+  pub const TYPES: &[&str] = &[
+    "A", "AAAA", "CAA", "CERT", "CNAME", "DNSKEY", "DS", "HTTPS", "LOC", "MX",
+    "NAPTR", "NS", "PTR", "SMIMEA", "SRV", "SSHFP", "SVCB", "TLSA", "TXT",
+    "URI",
+  ];
+}
+
+pub struct Provider {
+  api: String,
+  token: String,
+  domain: String,
+  zone_id: String,
+  client: reqwest::blocking::Client,
+}
+
+/// Full record name of `sub_domain`, where `@` is the domain itself.
+fn full_name(sub_domain: &str, domain: &str) -> String {
+  if sub_domain == "@" || sub_domain.is_empty() {
+    String::from(domain)
+  } else {
+    format!("{}.{}", sub_domain, domain)
+  }
+}
+
+/// Record line is used to choose whether the record is proxied.
+fn proxied(record_line: &str) -> bool {
+  record_line.eq_ignore_ascii_case(intra_common::LINE_PROXIED)
+}
+
+fn record_parse(record: &Value, domain: &str) -> Option<Record> {
+  let id = String::from(record.get("id")?.as_str()?);
+  let name = record.get("name")?.as_str()?;
+  let sub_domain = if name == domain {
+    String::from("@")
+  } else {
+    String::from(name.strip_suffix(domain)?.strip_suffix('.')?)
+  };
+  let value = String::from(record.get("content")?.as_str()?);
+  let r_type = String::from(record.get("type")?.as_str()?);
+  let (r_line, line_id) =
+    if record.get("proxied").and_then(Value::as_bool) == Some(true) {
+      (String::from(intra_common::LINE_PROXIED), None)
+    } else {
+      (
+        String::from(intra_common::LINE_DEFAULT),
+        Some(String::from(intra_common::LINE_ID_DEFAULT)),
+      )
+    };
+  // the automatic TTL is left to the provider
+  let ttl = record
+    .get("ttl")
+    .and_then(Value::as_u64)
+    .map(|t| t as u32)
+    .filter(|t| *t != intra_common::CLOUDFLARE_TTL_AUTO);
+  let mx = record
+    .get("priority")
+    .and_then(Value::as_u64)
+    .map(|p| p as u16);
+  let remark = record
+    .get("comment")
+    .and_then(Value::as_str)
+    .map(String::from);
+  Some(Record {
+    id,
+    sub_domain,
+    value,
+    r_type,
+    r_line,
+    line_id,
+    ttl,
+    mx,
+    weight: None,
+    status: None,
+    remark,
+  })
+}
+
+/// Add the fields of the spec which are set to the payload, the remark
+/// being the comment of the record.
+fn spec_payload(payload: &mut Value, spec: &RecordSpec) {
+  spec.warn_unsupported("cloudflare", intra_common::SPEC_FIELDS);
+  if let Some(ttl) = spec.ttl {
+    payload["ttl"] = json!(ttl);
+  }
+  if let Some(mx) = spec.mx {
+    payload["priority"] = json!(mx);
+  }
+  if let Some(remark) = &spec.remark {
+    payload["comment"] = json!(remark);
+  }
+}
+
+impl Provider {
+  /// Build a provider against the API at `api`, resolving the zone id of
+  /// `domain`.
+  pub fn with_api(api: &str, token: String, domain: String) -> Result<Self> {
+    let mut provider = Provider {
+      api: String::from(api.trim_end_matches('/')),
+      token,
+      domain,
+      zone_id: String::new(),
+      client: reqwest::blocking::Client::new(),
+    };
+
+    let zones = provider.request(
+      Method::GET,
+      "zones",
+      &[("name", provider.domain.as_str())],
+      None::<Value>,
+    )?;
+    provider.zone_id = zones
+      .get(0)
+      .and_then(|zone| zone.get("id"))
+      .and_then(Value::as_str)
+      .map(String::from)
+      .ok_or_else(|| {
+        Error::Reason(format!("No such zone: {}", provider.domain))
+      })?;
+    tracing::debug!("ZONE: {} {}", provider.domain, provider.zone_id);
+
+    Ok(provider)
+  }
+
+  /// Send a request and return the `result` field of the response.
+  fn request<P>(
+    &self,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+    payload: Option<P>,
+  ) -> Result<Value>
+  where
+    P: Serialize,
+  {
+    let url = format!("{}/{}", self.api, path);
+    let headers = {
+      let mut headers = HeaderMap::new();
+      headers
+        .insert("Authorization", format!("Bearer {}", self.token).parse()?);
+      headers.insert("Content-Type", "application/json".parse()?);
+      headers.insert("User-Agent", CAO_USER_AGENT.parse()?);
+      headers
+    };
+
+    tracing::debug!("URL: {} {}", method, url);
+    tracing::debug!("QUERY: {:?}", query);
+
+    let mut request = self
+      .client
+      .request(method, &url)
+      .headers(headers)
+      .query(query);
+    if let Some(payload) = payload {
+      let body = serde_json::to_string(&payload)?;
+      tracing::debug!("BODY: {}", body);
+      request = request.body(body);
+    }
+    let result: Value = request.send()?.json()?;
+
+    //
+    // Result process
+    #[cfg(debug_assertions)]
+    eprintln!("{}", serde_json::to_string_pretty(&result)?);
+
+    if result.get("success").and_then(Value::as_bool) == Some(true) {
+      Ok(result.get("result").cloned().unwrap_or(Value::Null))
+    } else {
+      Err(Error::http_failed(format!("Request failed {:?}", &result)))
+    }
+  }
+
+  fn record_path(&self, id: Option<&str>) -> String {
+    match id {
+      Some(id) => format!("zones/{}/dns_records/{}", self.zone_id, id),
+      None => format!("zones/{}/dns_records", self.zone_id),
+    }
+  }
+
+  fn result_id(result: &Value) -> Result<String> {
+    result
+      .get("id")
+      .and_then(Value::as_str)
+      .map(String::from)
+      .ok_or_else(|| {
+        Error::http_failed(format!("Record id parse failed {:?}", result))
+      })
+  }
+}
+
+impl DnsProviderBuild for Provider {
+  fn build_provider(token: String, domain: String) -> Result<Self> {
+    Provider::with_api(intra_common::CLOUDFLARE_API, token, domain)
+  }
+}
+
+impl DnsProvider for Provider {
+  fn add_record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let mut payload = json!({
+      "type": record_type,
+      "name": full_name(sub_domain, &self.domain),
+      "content": value,
+      "ttl": intra_common::CLOUDFLARE_TTL_AUTO,
+      "proxied": proxied(record_line),
+    });
+    spec_payload(&mut payload, spec);
+
+    let result = self.request(
+      Method::POST,
+      &self.record_path(None),
+      &[],
+      Some(payload),
+    )?;
+    Self::result_id(&result)
+  }
+
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    let offset = offset.unwrap_or(0).max(0) as usize;
+    let length = length.map_or(usize::MAX, |l| l.max(0) as usize);
+    let name = sub_domain.map(|s| full_name(s, &self.domain));
+    let per_page = intra_common::CLOUDFLARE_PAGE.to_string();
+
+    // Cloudflare pages by number, fetch the pages covering the range.
+    let mut page = offset / intra_common::CLOUDFLARE_PAGE + 1;
+    let mut skip = offset % intra_common::CLOUDFLARE_PAGE;
+    let mut records = Vec::new();
+    while records.len() < length {
+      let page_str = page.to_string();
+      let mut query =
+        vec![("page", page_str.as_str()), ("per_page", per_page.as_str())];
+      if let Some(name) = &name {
+        query.push(("name", name.as_str()));
+      }
+      let result = self.request(
+        Method::GET,
+        &self.record_path(None),
+        &query,
+        None::<Value>,
+      )?;
+      let list = match result {
+        Value::Array(list) => list,
+        _ => {
+          return Err(Error::http_failed(format!(
+            "Failed to parse result {:?}",
+            &result
+          )))
+        }
+      };
+
+      let count = list.len();
+      let remain = length - records.len();
+      records.extend(
+        list
+          .iter()
+          .skip(skip)
+          .filter_map(|r| record_parse(r, &self.domain))
+          .take(remain),
+      );
+      if count < intra_common::CLOUDFLARE_PAGE {
+        break;
+      }
+      page += 1;
+      skip = 0;
+    }
+
+    Ok(records)
+  }
+
+  fn modify_record(
+    &self,
+    record_id: &str,
+    sub_domain: Option<&str>,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let mut payload = json!({
+      "type": record_type,
+      "content": value,
+      "proxied": proxied(record_line),
+    });
+    if let Some(sub_domain) = sub_domain {
+      payload["name"] = json!(full_name(sub_domain, &self.domain));
+    }
+    spec_payload(&mut payload, spec);
+
+    let result = self.request(
+      Method::PATCH,
+      &self.record_path(Some(record_id)),
+      &[],
+      Some(payload),
+    )?;
+    Self::result_id(&result)
+  }
+
+  fn delete_record(&self, id: &str) -> Result<()> {
+    self.request(
+      Method::DELETE,
+      &self.record_path(Some(id)),
+      &[],
+      None::<Value>,
+    )?;
+    Ok(())
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    intra_common::SPEC_FIELDS
+  }
+
+  /// Proxied records have no counterpart on other providers.
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    Some(String::from(COMMON_LINE_DEFAULT))
+      .filter(|_| record_line == intra_common::LINE_DEFAULT)
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    Some(String::from(intra_common::LINE_DEFAULT))
+      .filter(|_| common_line == COMMON_LINE_DEFAULT)
+  }
+
+  /// SPF records are TXT records on Cloudflare.
+  fn provider_type(&self, common_type: &str) -> Option<String> {
+    match common_type.to_uppercase().as_str() {
+      "SPF" => Some(String::from("TXT")),
+      r_type if intra_common::TYPES.contains(&r_type) => {
+        Some(String::from(r_type))
+      }
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::Provider;
+  use crate::mock::http_server;
+  use crate::provider::interface::{
+    DnsProvider, Record, RecordSpec, RecordStatus,
+  };
+  use serde_json::{json, Value};
+
+  fn response(result: Value) -> String {
+    json!({
+      "success": true,
+      "errors": [],
+      "messages": [],
+      "result": result,
+    })
+    .to_string()
+  }
+
+  #[test]
+  fn test_cloudflare_record_actions() {
+    let (url, server) = http_server(vec![
+      response(json!([{ "id": "zone0", "name": "example.com" }])),
+      response(json!({ "id": "rec1" })),
+      response(json!([
+        {
+          "id": "rec1",
+          "name": "www.example.com",
+          "type": "A",
+          "content": "1.2.3.4",
+          "proxied": true,
+          "ttl": 1,
+        },
+        {
+          "id": "rec2",
+          "name": "example.com",
+          "type": "MX",
+          "content": "mail.example.com",
+          "proxied": false,
+          "ttl": 3600,
+          "priority": 10,
+          "comment": "mail",
+        },
+      ])),
+      response(json!({ "id": "rec1" })),
+      response(json!({ "id": "rec1" })),
+    ]);
+
+    let provider =
+      Provider::with_api(&url, String::from("token"), "example.com".into())
+        .unwrap();
+    let id = provider
+      .add_record("www", "A", "proxied", "1.2.3.4", &RecordSpec::default())
+      .unwrap();
+    assert_eq!(id, "rec1");
+
+    let records = provider.list_record(None, None, None).unwrap();
+    assert_eq!(
+      records,
+      vec![
+        Record {
+          id: String::from("rec1"),
+          sub_domain: String::from("www"),
+          value: String::from("1.2.3.4"),
+          r_type: String::from("A"),
+          r_line: String::from("proxied"),
+          line_id: None,
+          ttl: None,
+          mx: None,
+          weight: None,
+          status: None,
+          remark: None,
+        },
+        Record {
+          id: String::from("rec2"),
+          sub_domain: String::from("@"),
+          value: String::from("mail.example.com"),
+          r_type: String::from("MX"),
+          r_line: String::from("default"),
+          line_id: Some(String::from("0")),
+          ttl: Some(3600),
+          mx: Some(10),
+          weight: None,
+          status: None,
+          remark: Some(String::from("mail")),
+        },
+      ]
+    );
+
+    provider
+      .modify_record(
+        &id,
+        None,
+        "A",
+        "default",
+        "2.3.4.5",
+        &RecordSpec {
+          ttl: Some(300),
+          ..RecordSpec::default()
+        },
+      )
+      .unwrap();
+    // no request is sent
+    assert!(provider
+      .set_record_status(&id, RecordStatus::Disabled)
+      .is_err());
+    provider.delete_record(&id).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[0].path, "/zones?name=example.com");
+    assert_eq!(requests[0].header("Authorization"), Some("Bearer token"));
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].path, "/zones/zone0/dns_records");
+    let body: Value = serde_json::from_str(&requests[1].body).unwrap();
+    assert_eq!(body["name"], "www.example.com");
+    assert_eq!(body["proxied"], true);
+    assert_eq!(
+      requests[2].path,
+      "/zones/zone0/dns_records?page=1&per_page=100"
+    );
+    assert_eq!(requests[3].method, "PATCH");
+    assert_eq!(requests[3].path, "/zones/zone0/dns_records/rec1");
+    let body: Value = serde_json::from_str(&requests[3].body).unwrap();
+    assert_eq!(body.get("name"), None);
+    assert_eq!(body["content"], "2.3.4.5");
+    assert_eq!(body["ttl"], 300);
+    assert_eq!(requests[4].method, "DELETE");
+  }
+}
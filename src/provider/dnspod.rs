@@ -11,13 +11,6 @@ use super::interface::*;
 use crate::error::{Error, Result};
 
 mod intra_common {
-
-  pub const CAO_USER_AGENT: &str = concat!(
-    "cao/",
-    env!("CARGO_PKG_VERSION"),
-    ", Johann Li <me@qinka.pro>"
-  );
-
   // pub const CAO_FORM_TOKEN: &str = "login_token";
   pub const CAO_FORM_DOMAIN: &str = "Domain";
   pub const CAO_FORM_SDOMAIN: &str = "SubDomain";
@@ -28,6 +21,12 @@ mod intra_common {
   pub const CAO_FORM_RLINE_ID: &str = "RecordLineId";
   pub const CAO_FORM_OFFSET: &str = "Offset";
   pub const CAO_FORM_LENGTH: &str = "Limit";
+  pub const CAO_FORM_KEYWORD: &str = "Keyword";
+  pub const CAO_FORM_TTL: &str = "TTL";
+  pub const CAO_FORM_MX: &str = "MX";
+  pub const CAO_FORM_WEIGHT: &str = "Weight";
+  pub const CAO_FORM_STATUS: &str = "Status";
+  pub const CAO_FORM_REMARK: &str = "Remark";
 
   pub const DNSPOD_API: &str = "https://dnspod.tencentcloudapi.com";
   pub const DNSPOD_HOST: &str = "dnspod.tencentcloudapi.com";
@@ -35,6 +34,8 @@ mod intra_common {
   pub const DNSPOD_REQUEST: &str = "tc3_request";
   pub const DNSPOD_ALGORITHM: &str = "TC3-HMAC-SHA256";
   pub const DNSPOD_API_VERSION: &str = "2021-03-23";
+  /// Error code of DescribeRecordList when no record matches.
+  pub const DNSPOD_NO_RECORD: &str = "ResourceNotFound.NoDataOfRecord";
 }
 
 #[inline]
@@ -69,30 +70,105 @@ fn hash_sha256_hex(data: &[u8]) -> Result<String> {
 }
 
 fn record_parse(record: &Value) -> Option<Record> {
-  let id = record.get("RecordId")?.as_u64()?;
+  let id = record.get("RecordId")?.as_u64()?.to_string();
   let sub_domain = String::from(record.get("Name")?.as_str()?);
   let value = String::from(record.get("Value")?.as_str()?);
   let r_type = String::from(record.get("Type")?.as_str()?);
   let r_line = String::from(record.get("Line")?.as_str()?);
+  let line_id = record
+    .get("LineId")
+    .and_then(Value::as_str)
+    .map(String::from);
+  let ttl = record
+    .get("TTL")
+    .and_then(Value::as_u64)
+    .map(|ttl| ttl as u32);
+  // MX is 0 for the other types
+  let mx = record
+    .get("MX")
+    .and_then(Value::as_u64)
+    .filter(|_| r_type.eq_ignore_ascii_case("MX"))
+    .map(|mx| mx as u16);
+  let weight = record
+    .get("Weight")
+    .and_then(Value::as_u64)
+    .map(|weight| weight as u32);
+  let status = record
+    .get("Status")
+    .and_then(Value::as_str)
+    .and_then(|status| status.parse().ok());
+  let remark = record
+    .get("Remark")
+    .and_then(Value::as_str)
+    .filter(|remark| !remark.is_empty())
+    .map(String::from);
   Some(Record {
     id,
     sub_domain,
     value,
     r_type,
     r_line,
+    line_id,
+    ttl,
+    mx,
+    weight,
+    status,
+    remark,
   })
 }
 
+/// Add the fields of the spec which are set to the payload.
+fn spec_payload(data: &mut Value, spec: &RecordSpec) {
+  if let Some(ttl) = spec.ttl {
+    data[intra_common::CAO_FORM_TTL] = json!(ttl);
+  }
+  if let Some(mx) = spec.mx {
+    data[intra_common::CAO_FORM_MX] = json!(mx);
+  }
+  if let Some(weight) = spec.weight {
+    data[intra_common::CAO_FORM_WEIGHT] = json!(weight);
+  }
+  if let Some(status) = spec.status {
+    data[intra_common::CAO_FORM_STATUS] = json!(status_name(status));
+  }
+  if let Some(remark) = &spec.remark {
+    data[intra_common::CAO_FORM_REMARK] = json!(remark);
+  }
+}
+
+fn status_name(status: RecordStatus) -> &'static str {
+  match status {
+    RecordStatus::Enabled => "ENABLE",
+    RecordStatus::Disabled => "DISABLE",
+  }
+}
+
 pub struct Provider {
   /// secret_id
   id: String,
   /// secret_key
   key: String,
   domain: String,
+  api: String,
   client: reqwest::blocking::Client,
 }
 
 impl Provider {
+  /// Build a provider against the API at `api`.
+  pub fn with_api(api: &str, token: String, domain: String) -> Result<Self> {
+    let client = reqwest::blocking::Client::new();
+    let mut split = token.split(',');
+    let id = split.next().unwrap().to_string();
+    let key = split.next().unwrap().to_string();
+    Ok(Provider {
+      id,
+      key,
+      domain,
+      api: String::from(api),
+      client,
+    })
+  }
+
   fn make_authorization(
     &self,
     action: &str,
@@ -184,20 +260,20 @@ impl Provider {
       headers.insert("X-TC-Action", action.parse()?);
       headers.insert("X-TC-Timestamp", timestamp.to_string().parse()?);
       headers.insert("X-TC-Version", intra_common::DNSPOD_API_VERSION.parse()?);
-      headers.insert("User-Agent", intra_common::CAO_USER_AGENT.parse()?);
+      headers.insert("User-Agent", CAO_USER_AGENT.parse()?);
       headers
     };
 
     tracing::debug!("TIMESTAMP: {}", timestamp);
     tracing::debug!("KEY: {} {}", self.id, self.key);
     tracing::debug!("DOMAIN: {}", self.domain);
-    tracing::debug!("URL: {:?}", intra_common::DNSPOD_API);
+    tracing::debug!("URL: {:?}", self.api);
     tracing::debug!("HEADER: {:?}", &headers);
     tracing::debug!("BODY: {}", serde_json::to_string(&payload)?);
 
     let result = self
       .client
-      .post(intra_common::DNSPOD_API)
+      .post(&self.api)
       .headers(headers)
       .body(serde_json::to_string(&payload)?)
       .send()?
@@ -205,82 +281,87 @@ impl Provider {
 
     Ok(result)
   }
-}
 
-impl DnsProviderBuild for Provider {
-  fn build_provider(token: String, domain: String) -> Result<Self> {
-    let client = reqwest::blocking::Client::new();
-    let mut split = token.split(',');
-    let id = split.next().unwrap().to_string();
-    let key = split.next().unwrap().to_string();
-    Ok(Provider {
-      id,
-      key,
-      domain,
-      client,
-    })
-  }
-}
-
-impl DnsProvider for Provider {
-  fn add_record(
-    &self,
-    sub_domain: &str,
-    record_type: &str,
-    record_line: &str,
-    value: &str,
-  ) -> Result<u64> {
-    //
-    // Build request
-    // payload
-    let (record_line_key, record_line_value) =
+  /// Action and payload of the request making the change.
+  fn change_payload(&self, change: &Change) -> Result<(&'static str, Value)> {
+    let record_line = |record_line: &str| -> Result<(&str, Value)> {
       if record_line.chars().all(char::is_numeric) {
-        (
+        Ok((
           intra_common::CAO_FORM_RLINE_ID,
           json!(record_line.parse::<i32>()?),
-        )
+        ))
       } else {
-        (intra_common::CAO_FORM_RLINE, json!(record_line))
-      };
-    let payload = json!({
-        intra_common::CAO_FORM_DOMAIN: &self.domain,
-        intra_common::CAO_FORM_SDOMAIN: sub_domain,
-        intra_common::CAO_FORM_RTYPE: record_type,
-        intra_common::CAO_FORM_VALUE: value,
-        record_line_key: record_line_value,
-    });
-
-    let result: Value = self.request("CreateRecord", payload)?;
-
-    //
-    // Result process
-    #[cfg(debug_assertions)]
-    eprintln!("{}", serde_json::to_string_pretty(&result)?);
+        Ok((intra_common::CAO_FORM_RLINE, json!(record_line)))
+      }
+    };
 
-    // response processing
-    if let Some(response) = result.get("Response") {
-      if let Some(id) = response.get("RecordId") {
-        id.as_u64().ok_or(Error::http_failed(format!(
-          "Record id parse failed {:?}",
-          &result
-        )))
-      } else {
-        Err(Error::http_failed(format!("Request failed {:?}", &result)))
+    match *change {
+      Change::Add {
+        sub_domain,
+        record_type,
+        record_line: r_line,
+        value,
+        spec,
+      } => {
+        let (record_line_key, record_line_value) = record_line(r_line)?;
+        let mut payload = json!({
+            intra_common::CAO_FORM_DOMAIN: &self.domain,
+            intra_common::CAO_FORM_SDOMAIN: sub_domain,
+            intra_common::CAO_FORM_RTYPE: record_type,
+            intra_common::CAO_FORM_VALUE: value,
+            record_line_key: record_line_value,
+        });
+        spec_payload(&mut payload, spec);
+        Ok(("CreateRecord", payload))
       }
-    } else {
-      Err(Error::http_failed(format!(
-        "Failed to parse result {:?}",
-        &result
-      )))
+      Change::Modify {
+        id,
+        sub_domain,
+        record_type,
+        record_line: r_line,
+        value,
+        spec,
+      } => {
+        let (record_line_key, record_line_value) = record_line(r_line)?;
+        let mut payload = json!({
+          intra_common::CAO_FORM_RID: id.parse::<u64>()?,
+          intra_common::CAO_FORM_DOMAIN: &self.domain,
+          intra_common::CAO_FORM_SDOMAIN: sub_domain,
+          intra_common::CAO_FORM_RTYPE: record_type,
+          intra_common::CAO_FORM_VALUE: value,
+          record_line_key: record_line_value,
+        });
+        spec_payload(&mut payload, spec);
+        Ok(("ModifyRecord", payload))
+      }
+      Change::Delete { id } => Ok((
+        "DeleteRecord",
+        json!({
+            intra_common::CAO_FORM_DOMAIN: &self.domain,
+            intra_common::CAO_FORM_RID: id.parse::<u64>()?,
+        }),
+      )),
+      Change::SetStatus { id, status } => Ok((
+        "ModifyRecordStatus",
+        json!({
+            intra_common::CAO_FORM_DOMAIN: &self.domain,
+            intra_common::CAO_FORM_RID: id.parse::<u64>()?,
+            intra_common::CAO_FORM_STATUS: status_name(status),
+        }),
+      )),
     }
   }
 
-  fn list_record(
+  /// `DescribeRecordList`, with the total count of matching records.
+  ///
+  /// Sub-domain, type, line and keyword of the filter are applied by the
+  /// API.
+  fn describe_record_list(
     &self,
     offset: Option<i32>,
     length: Option<i32>,
-    sub_domain: Option<&str>,
-  ) -> Result<Vec<Record>> {
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
     //
     // payload
     let payload = {
@@ -293,9 +374,22 @@ impl DnsProvider for Provider {
       if let Some(length) = length {
         data[intra_common::CAO_FORM_LENGTH] = json!(length);
       }
-      if let Some(sub_domain) = sub_domain {
+      if let Some(sub_domain) = &filter.sub_domain {
         data[intra_common::CAO_FORM_SDOMAIN] = json!(sub_domain);
       }
+      if let Some(record_type) = &filter.record_type {
+        data[intra_common::CAO_FORM_RTYPE] = json!(record_type);
+      }
+      if let Some(record_line) = &filter.record_line {
+        if record_line.chars().all(char::is_numeric) {
+          data[intra_common::CAO_FORM_RLINE_ID] = json!(record_line);
+        } else {
+          data[intra_common::CAO_FORM_RLINE] = json!(record_line);
+        }
+      }
+      if let Some(keyword) = &filter.keyword {
+        data[intra_common::CAO_FORM_KEYWORD] = json!(keyword);
+      }
       data
     };
 
@@ -309,7 +403,26 @@ impl DnsProvider for Provider {
     // response processing
     if let Some(response) = result.get("Response") {
       if let Some(Value::Array(list)) = response.get("RecordList") {
-        Ok(list.iter().filter_map(record_parse).collect())
+        let total = response
+          .get("RecordCountInfo")
+          .and_then(|info| info.get("TotalCount"))
+          .and_then(Value::as_u64)
+          .map(|total| total as usize);
+        Ok(RecordPage {
+          records: list.iter().filter_map(record_parse).collect(),
+          total,
+        })
+      } else if response
+        .get("Error")
+        .and_then(|error| error.get("Code"))
+        .and_then(Value::as_str)
+        == Some(intra_common::DNSPOD_NO_RECORD)
+      {
+        // no record matching is an error of the API
+        Ok(RecordPage {
+          records: Vec::new(),
+          total: Some(0),
+        })
       } else {
         Err(Error::http_failed(format!("Request failed {:?}", &result)))
       }
@@ -320,36 +433,98 @@ impl DnsProvider for Provider {
       )))
     }
   }
+}
+
+impl DnsProviderBuild for Provider {
+  fn build_provider(token: String, domain: String) -> Result<Self> {
+    Provider::with_api(intra_common::DNSPOD_API, token, domain)
+  }
+}
 
-  fn modify_record(
+impl DnsProvider for Provider {
+  fn add_record(
     &self,
-    record_id: u64,
-    sub_domain: Option<&str>,
+    sub_domain: &str,
     record_type: &str,
     record_line: &str,
     value: &str,
-  ) -> Result<u64> {
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let (action, payload) = self.change_payload(&Change::Add {
+      sub_domain,
+      record_type,
+      record_line,
+      value,
+      spec,
+    })?;
+    let result: Value = self.request(action, payload)?;
+
     //
-    // Payload
-    let (record_line_key, record_line_value) =
-      if record_line.chars().all(char::is_numeric) {
-        (
-          intra_common::CAO_FORM_RLINE_ID,
-          json!(record_line.parse::<i32>()?),
-        )
+    // Result process
+    #[cfg(debug_assertions)]
+    eprintln!("{}", serde_json::to_string_pretty(&result)?);
+
+    // response processing
+    if let Some(response) = result.get("Response") {
+      if let Some(id) = response.get("RecordId") {
+        id.as_u64()
+          .map(|id| id.to_string())
+          .ok_or(Error::http_failed(format!(
+            "Record id parse failed {:?}",
+            &result
+          )))
       } else {
-        (intra_common::CAO_FORM_RLINE, json!(record_line))
-      };
-    let payload: Value = json!({
-      intra_common::CAO_FORM_RID: record_id,
-      intra_common::CAO_FORM_DOMAIN: &self.domain,
-      intra_common::CAO_FORM_SDOMAIN: sub_domain,
-      intra_common::CAO_FORM_RTYPE: record_type,
-      intra_common::CAO_FORM_VALUE: value,
-      record_line_key: record_line_value,
-    });
+        Err(Error::http_failed(format!("Request failed {:?}", &result)))
+      }
+    } else {
+      Err(Error::http_failed(format!(
+        "Failed to parse result {:?}",
+        &result
+      )))
+    }
+  }
 
-    let result: Value = self.request("ModifyRecord", payload)?;
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    let filter = RecordFilter::sub_domain(sub_domain);
+    Ok(self.describe_record_list(offset, length, &filter)?.records)
+  }
+
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
+    self.describe_record_list(
+      Some(offset.try_into()?),
+      Some(length.try_into()?),
+      filter,
+    )
+  }
+
+  fn modify_record(
+    &self,
+    record_id: &str,
+    sub_domain: Option<&str>,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let (action, payload) = self.change_payload(&Change::Modify {
+      id: record_id,
+      sub_domain,
+      record_type,
+      record_line,
+      value,
+      spec,
+    })?;
+    let result: Value = self.request(action, payload)?;
 
     //
     // Result process
@@ -359,10 +534,12 @@ impl DnsProvider for Provider {
     // response processing
     if let Some(response) = result.get("Response") {
       if let Some(id) = response.get("RecordId") {
-        id.as_u64().ok_or(Error::http_failed(format!(
-          "Record id parse failed {:?}",
-          &result
-        )))
+        id.as_u64()
+          .map(|id| id.to_string())
+          .ok_or(Error::http_failed(format!(
+            "Record id parse failed {:?}",
+            &result
+          )))
       } else {
         Err(Error::http_failed(format!("Request failed {:?}", &result)))
       }
@@ -374,13 +551,9 @@ impl DnsProvider for Provider {
     }
   }
 
-  fn delete_record(&self, id: u64) -> Result<()> {
-    let payload = json!({
-        intra_common::CAO_FORM_DOMAIN: &self.domain,
-        intra_common::CAO_FORM_RID: id,
-    });
-
-    let result: Value = self.request("DeleteRecord", payload)?;
+  fn delete_record(&self, id: &str) -> Result<()> {
+    let (action, payload) = self.change_payload(&Change::Delete { id })?;
+    let result: Value = self.request(action, payload)?;
 
     //
     // Result process
@@ -397,16 +570,190 @@ impl DnsProvider for Provider {
       )))
     }
   }
+
+  fn change_request(&self, change: &Change) -> Result<(String, Value)> {
+    let (action, payload) = self.change_payload(change)?;
+    Ok((String::from(action), payload))
+  }
+
+  fn set_record_status(&self, id: &str, status: RecordStatus) -> Result<()> {
+    let (action, payload) =
+      self.change_payload(&Change::SetStatus { id, status })?;
+    let result: Value = self.request(action, payload)?;
+
+    //
+    // Result process
+    #[cfg(debug_assertions)]
+    eprintln!("{}", serde_json::to_string_pretty(&result)?);
+
+    // response processing
+    match result.get("Response") {
+      Some(response) if response.get("RecordId").is_some() => Ok(()),
+      Some(_) => {
+        Err(Error::http_failed(format!("Request failed {:?}", &result)))
+      }
+      None => Err(Error::http_failed(format!(
+        "Failed to parse result {:?}",
+        &result
+      ))),
+    }
+  }
+
+  fn supports_status(&self) -> bool {
+    true
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    SPEC_FIELDS
+  }
+
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    Some(String::from(record_line))
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    Some(String::from(common_line))
+  }
 }
 
 #[cfg(test)]
 mod test {
   use super::Provider;
+  use crate::mock::http_server;
   use crate::provider::interface::DnsProviderBuild;
-  use crate::provider::interface::{DnsProvider, Record};
+  use crate::provider::interface::{
+    Change, DnsProvider, Record, RecordSpec, RecordStatus,
+  };
+  use crate::sync::{sync_record, SyncResult};
+  use serde_json::json;
   use trust_dns_resolver::config::*;
   use trust_dns_resolver::Resolver;
 
+  #[test]
+  fn test_dnspod_sync_missing_record() {
+    let (url, server) = http_server(vec![
+      json!({ "Response": {
+        "Error": {
+          "Code": "ResourceNotFound.NoDataOfRecord",
+          "Message": "记录列表为空。",
+        },
+        "RequestId": "1",
+      }})
+      .to_string(),
+      json!({ "Response": { "RecordId": 162, "RequestId": "2" } }).to_string(),
+    ]);
+    let provider = Provider::with_api(
+      &url,
+      String::from("id,key"),
+      String::from("example.com"),
+    )
+    .unwrap();
+
+    let result = sync_record(
+      &provider,
+      "www",
+      "A",
+      "0",
+      "1.2.3.4",
+      &RecordSpec::default(),
+    );
+    assert_eq!(result.unwrap(), SyncResult::Created(String::from("162")));
+
+    let requests = server.join().unwrap();
+    assert_eq!(
+      requests[0].header("X-TC-Action"),
+      Some("DescribeRecordList")
+    );
+    assert_eq!(requests[1].header("X-TC-Action"), Some("CreateRecord"));
+    assert!(requests[1].body.contains("\"RecordLineId\":0"));
+  }
+
+  #[test]
+  fn test_dnspod_set_record_status() {
+    let (url, server) = http_server(vec![
+      json!({ "Response": { "RecordId": 162, "RequestId": "1" } }).to_string(),
+      json!({ "Response": {
+        "Error": {
+          "Code": "InvalidParameter.RecordIdInvalid",
+          "Message": "记录编号错误。",
+        },
+        "RequestId": "2",
+      }})
+      .to_string(),
+    ]);
+    let provider = Provider::with_api(
+      &url,
+      String::from("id,key"),
+      String::from("example.com"),
+    )
+    .unwrap();
+
+    provider
+      .set_record_status("162", RecordStatus::Disabled)
+      .unwrap();
+    assert!(provider
+      .set_record_status("163", RecordStatus::Enabled)
+      .is_err());
+    assert!(provider
+      .set_record_status("www", RecordStatus::Enabled)
+      .is_err());
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+      requests[0].header("X-TC-Action"),
+      Some("ModifyRecordStatus")
+    );
+    let body: serde_json::Value =
+      serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(
+      body,
+      json!({ "Domain": "example.com", "RecordId": 162, "Status": "DISABLE" })
+    );
+    let body: serde_json::Value =
+      serde_json::from_str(&requests[1].body).unwrap();
+    assert_eq!(body["Status"], "ENABLE");
+  }
+
+  #[test]
+  fn test_dnspod_change_request() {
+    let provider = Provider::build_provider(
+      String::from("id,key"),
+      String::from("example.com"),
+    )
+    .unwrap();
+    let spec = RecordSpec {
+      ttl: Some(600),
+      status: Some(RecordStatus::Disabled),
+      ..RecordSpec::default()
+    };
+    let (action, payload) = provider
+      .change_request(&Change::Add {
+        sub_domain: "www",
+        record_type: "A",
+        record_line: "0",
+        value: "1.2.3.4",
+        spec: &spec,
+      })
+      .unwrap();
+    assert_eq!(action, "CreateRecord");
+    assert_eq!(
+      payload,
+      json!({
+        "Domain": "example.com",
+        "SubDomain": "www",
+        "RecordType": "A",
+        "Value": "1.2.3.4",
+        "RecordLineId": 0,
+        "TTL": 600,
+        "Status": "DISABLE",
+      })
+    );
+    assert!(provider
+      .change_request(&Change::Delete { id: "www" })
+      .is_err());
+  }
+
   #[test]
   fn test_dnspod_record_actions() {
     use std::env::var;
@@ -458,7 +805,13 @@ mod test {
     }
 
     let id = provider
-      .add_record(&sub_domain, &type_a, &r_line_id, &value_a)
+      .add_record(
+        &sub_domain,
+        &type_a,
+        &r_line_id,
+        &value_a,
+        &RecordSpec::default(),
+      )
       .unwrap();
 
     sleep(sleep_time);
@@ -475,18 +828,31 @@ mod test {
     assert_eq!(
       records[0],
       Record {
-        id: id,
+        id: id.clone(),
         sub_domain: sub_domain.clone(),
         value: String::from("1.2.3.4"),
         r_type: String::from("A"),
         r_line: String::from("默认"),
+        line_id: Some(String::from("0")),
+        ttl: Some(600),
+        mx: None,
+        weight: None,
+        status: Some(RecordStatus::Enabled),
+        remark: None,
       }
     );
 
     sleep(sleep_time);
 
     provider
-      .modify_record(id, Some(&sub_domain), &type_a, &r_line_id, &value_b)
+      .modify_record(
+        &id,
+        Some(&sub_domain),
+        &type_a,
+        &r_line_id,
+        &value_b,
+        &RecordSpec::default(),
+      )
       .unwrap();
 
     sleep(sleep_time);
@@ -495,7 +861,7 @@ mod test {
     let address = response.iter().next().expect("no addresses returned!");
     assert_eq!(address, IpAddr::V4(Ipv4Addr::new(2, 3, 4, 5)));
 
-    provider.delete_record(id).unwrap();
+    provider.delete_record(&id).unwrap();
 
     sleep(sleep_time);
     sleep(sleep_time);
@@ -0,0 +1,174 @@
+//! Provider logging the changes instead of making them, for `--dry-run`.
+//!
+//! Listing still goes to the wrapped provider, so that the changes are the
+//! ones a real run would make.
+
+use serde_json::Value;
+
+use super::interface::*;
+use super::BoxDnsProvider;
+use crate::error::{Error, Result};
+
+/// Id returned for the records which would be added.
+pub const DRY_RUN_ID: &str = "dry-run";
+
+pub struct Provider {
+  inner: BoxDnsProvider,
+}
+
+impl Provider {
+  pub fn new(inner: BoxDnsProvider) -> Self {
+    Provider { inner }
+  }
+
+  fn log(&self, change: &Change) -> Result<()> {
+    let (action, payload) = self.inner.change_request(change)?;
+    tracing::info!("dry run, not sent: {} {}", action, payload);
+    Ok(())
+  }
+}
+
+impl DnsProvider for Provider {
+  fn add_record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    self.log(&Change::Add {
+      sub_domain,
+      record_type,
+      record_line,
+      value,
+      spec,
+    })?;
+    Ok(String::from(DRY_RUN_ID))
+  }
+
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    self.inner.list_record(offset, length, sub_domain)
+  }
+
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
+    self.inner.list_record_page(offset, length, filter)
+  }
+
+  fn modify_record(
+    &self,
+    id: &str,
+    sub_domain: Option<&str>,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    self.log(&Change::Modify {
+      id,
+      sub_domain,
+      record_type,
+      record_line,
+      value,
+      spec,
+    })?;
+    Ok(String::from(id))
+  }
+
+  fn delete_record(&self, id: &str) -> Result<()> {
+    self.log(&Change::Delete { id })
+  }
+
+  fn change_request(&self, change: &Change) -> Result<(String, Value)> {
+    self.inner.change_request(change)
+  }
+
+  /// Fails as the wrapped provider would when it has no status.
+  fn set_record_status(&self, id: &str, status: RecordStatus) -> Result<()> {
+    if !self.inner.supports_status() {
+      return Err(Error::unsupported("Setting the record status"));
+    }
+    self.log(&Change::SetStatus { id, status })
+  }
+
+  fn supports_status(&self) -> bool {
+    self.inner.supports_status()
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    self.inner.spec_fields()
+  }
+
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    self.inner.common_line(record_line)
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    self.inner.provider_line(common_line)
+  }
+
+  fn common_type(&self, record_type: &str) -> Option<String> {
+    self.inner.common_type(record_type)
+  }
+
+  fn provider_type(&self, common_type: &str) -> Option<String> {
+    self.inner.provider_type(common_type)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::memory;
+
+  #[test]
+  fn test_dry_run() {
+    let inner = memory::Provider::default();
+    let spec = RecordSpec::default();
+    let id = inner
+      .add_record("www", "A", "默认", "1.2.3.4", &spec)
+      .unwrap();
+    let provider = Provider::new(Box::new(inner));
+
+    let added = provider
+      .add_record("home", "A", "默认", "1.2.3.4", &spec)
+      .unwrap();
+    assert_eq!(added, DRY_RUN_ID);
+    provider
+      .modify_record(&id, None, "A", "默认", "2.3.4.5", &spec)
+      .unwrap();
+    provider
+      .set_record_status(&id, RecordStatus::Disabled)
+      .unwrap();
+    provider.delete_record(&id).unwrap();
+
+    let records = provider.list_record(None, None, None).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].value, "1.2.3.4");
+    assert_eq!(records[0].status, None);
+
+    let (action, payload) = provider
+      .change_request(&Change::Delete { id: &id })
+      .unwrap();
+    assert_eq!(action, "delete_record");
+    assert_eq!(payload, serde_json::json!({ "id": "1" }));
+
+    let provider =
+      Provider::new(Box::new(memory::Provider::without_lines(vec![
+        memory::record("1", "www", "A", "1.2.3.4"),
+      ])));
+    assert!(provider
+      .set_record_status("1", RecordStatus::Disabled)
+      .is_err());
+  }
+}
@@ -1,12 +1,81 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
 use crate::error::Error;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordStatus {
+  Enabled,
+  Disabled,
+}
+
+impl FromStr for RecordStatus {
+  type Err = Error;
+
+  fn from_str(status: &str) -> Result<Self, Self::Err> {
+    match status.to_ascii_lowercase().as_str() {
+      "enabled" | "enable" => Ok(RecordStatus::Enabled),
+      "disabled" | "disable" => Ok(RecordStatus::Disabled),
+      _ => Err(Error::Reason(format!(
+        "Bad status {}, expected enabled or disabled",
+        status
+      ))),
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for RecordStatus {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(serde::de::Error::custom)
+  }
+}
+
+impl std::fmt::Display for RecordStatus {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      RecordStatus::Enabled => write!(f, "enabled"),
+      RecordStatus::Disabled => write!(f, "disabled"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Record {
-  pub id: u64,
+  pub id: String,
   pub sub_domain: String,
   pub value: String,
+  #[serde(rename = "type")]
   pub r_type: String,
+  #[serde(rename = "line")]
   pub r_line: String,
+  /// Id of the line, for providers having ids of lines too.
+  pub line_id: Option<String>,
+  pub ttl: Option<u32>,
+  /// Priority of MX records.
+  pub mx: Option<u16>,
+  pub weight: Option<u32>,
+  /// Unknown when the provider has no such notion, the record is then
+  /// served.
+  pub status: Option<RecordStatus>,
+  pub remark: Option<String>,
+}
+
+impl Record {
+  /// Whether the record is on `record_line`, given by name or by id.
+  pub fn on_line(&self, record_line: &str) -> bool {
+    !record_line.is_empty()
+      && (self.r_line == record_line
+        || self.line_id.as_deref() == Some(record_line))
+  }
 }
 
 impl std::fmt::Display for Record {
@@ -19,6 +88,200 @@ impl std::fmt::Display for Record {
   }
 }
 
+/// User agent of the requests to the APIs and the address sources.
+pub const CAO_USER_AGENT: &str = concat!(
+  "cao/",
+  env!("CARGO_PKG_VERSION"),
+  ", Johann Li <me@qinka.pro>"
+);
+
+/// Name on DNSPod of the default line, see `DnsProvider::common_line`.
+pub const COMMON_LINE_DEFAULT: &str = "默认";
+
+/// Names of the fields of `RecordSpec`.
+pub const SPEC_FIELDS: &[&str] = &["ttl", "mx", "weight", "status", "remark"];
+
+/// Optional fields of a record to add or modify, unset fields are left to
+/// the provider defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecordSpec {
+  pub ttl: Option<u32>,
+  /// Priority of MX records.
+  pub mx: Option<u16>,
+  pub weight: Option<u32>,
+  pub status: Option<RecordStatus>,
+  pub remark: Option<String>,
+}
+
+impl RecordSpec {
+  /// Names of the fields which are set.
+  pub fn fields(&self) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if self.ttl.is_some() {
+      fields.push("ttl");
+    }
+    if self.mx.is_some() {
+      fields.push("mx");
+    }
+    if self.weight.is_some() {
+      fields.push("weight");
+    }
+    if self.status.is_some() {
+      fields.push("status");
+    }
+    if self.remark.is_some() {
+      fields.push("remark");
+    }
+    fields
+  }
+
+  /// Warn about the fields which are set but not sent by the provider.
+  pub fn warn_unsupported(&self, provider: &str, supported: &[&str]) {
+    for field in self.fields() {
+      if !supported.contains(&field) {
+        tracing::warn!("{} is not supported by {}, ignored", field, provider);
+      }
+    }
+  }
+
+  /// Whether the record already has every field which is set.
+  pub fn satisfied_by(&self, record: &Record) -> bool {
+    self.ttl.is_none_or(|ttl| record.ttl == Some(ttl))
+      && self.mx.is_none_or(|mx| record.mx == Some(mx))
+      && self
+        .weight
+        .is_none_or(|weight| record.weight == Some(weight))
+      && self.status.is_none_or(|status| {
+        record.status.unwrap_or(RecordStatus::Enabled) == status
+      })
+      && self
+        .remark
+        .as_ref()
+        .is_none_or(|remark| record.remark.as_ref() == Some(remark))
+  }
+}
+
+/// A change of the records, as done by one of the mutating methods of
+/// `DnsProvider`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Change<'a> {
+  Add {
+    sub_domain: &'a str,
+    #[serde(rename = "type")]
+    record_type: &'a str,
+    #[serde(rename = "line")]
+    record_line: &'a str,
+    value: &'a str,
+    spec: &'a RecordSpec,
+  },
+  Modify {
+    id: &'a str,
+    sub_domain: Option<&'a str>,
+    #[serde(rename = "type")]
+    record_type: &'a str,
+    #[serde(rename = "line")]
+    record_line: &'a str,
+    value: &'a str,
+    spec: &'a RecordSpec,
+  },
+  Delete {
+    id: &'a str,
+  },
+  SetStatus {
+    id: &'a str,
+    status: RecordStatus,
+  },
+}
+
+impl Change<'_> {
+  /// Name of the method making the change.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Change::Add { .. } => "add_record",
+      Change::Modify { .. } => "modify_record",
+      Change::Delete { .. } => "delete_record",
+      Change::SetStatus { .. } => "set_record_status",
+    }
+  }
+}
+
+/// How the value of a record is matched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueMatch {
+  Exact(String),
+  Contains(String),
+  /// Address in the network.
+  Network(IpNet),
+}
+
+/// Records to list, empty fields matching every record.
+///
+/// Providers apply the parts their API supports, `matches` is checked on
+/// every listed record for the others.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordFilter {
+  pub sub_domain: Option<String>,
+  pub record_type: Option<String>,
+  pub record_line: Option<String>,
+  pub value: Option<ValueMatch>,
+  pub status: Option<RecordStatus>,
+  /// Text found in the sub-domain or the value.
+  pub keyword: Option<String>,
+}
+
+impl RecordFilter {
+  pub fn sub_domain(sub_domain: Option<&str>) -> Self {
+    RecordFilter {
+      sub_domain: sub_domain.map(String::from),
+      ..RecordFilter::default()
+    }
+  }
+
+  pub fn matches(&self, record: &Record) -> bool {
+    let value = match &self.value {
+      None => true,
+      Some(ValueMatch::Exact(value)) => record.value == *value,
+      Some(ValueMatch::Contains(text)) => record.value.contains(text.as_str()),
+      Some(ValueMatch::Network(net)) => record
+        .value
+        .parse::<IpAddr>()
+        .is_ok_and(|ip| net.contains(&ip)),
+    };
+    value
+      && self
+        .sub_domain
+        .as_ref()
+        .is_none_or(|sub_domain| record.sub_domain == *sub_domain)
+      && self
+        .record_type
+        .as_ref()
+        .is_none_or(|r_type| record.r_type.eq_ignore_ascii_case(r_type))
+      && self
+        .record_line
+        .as_ref()
+        .is_none_or(|r_line| record.on_line(r_line))
+      && self.status.is_none_or(|status| {
+        record.status.unwrap_or(RecordStatus::Enabled) == status
+      })
+      && self.keyword.as_ref().is_none_or(|keyword| {
+        record.sub_domain.contains(keyword.as_str())
+          || record.value.contains(keyword.as_str())
+      })
+  }
+}
+
+/// A page of records.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordPage {
+  pub records: Vec<Record>,
+  /// Number of records matching the query, when the provider reports it.
+  pub total: Option<usize>,
+}
+
+/// Number of records asked per page when listing every record.
+pub const LIST_PAGE_SIZE: usize = 100;
+
 pub trait DnsProviderBuild: Sized {
   fn build_provider(key: String, domain: String) -> Result<Self, Error>;
 }
@@ -30,7 +293,8 @@ pub trait DnsProvider {
     record_type: &str,
     record_line: &str,
     value: &str,
-  ) -> Result<u64, Error>;
+    spec: &RecordSpec,
+  ) -> Result<String, Error>;
 
   fn list_record(
     &self,
@@ -39,14 +303,263 @@ pub trait DnsProvider {
     sub_domain: Option<&str>,
   ) -> Result<Vec<Record>, Error>;
 
+  /// A page of records with the total count of records.
+  ///
+  /// Providers apply the parts of the filter their API supports, records
+  /// of the page may not match the others. Providers reporting the total
+  /// count override this, the default only filters by sub-domain and does
+  /// not know the count.
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage, Error> {
+    let records = self.list_record(
+      Some(offset.min(i32::MAX as usize) as i32),
+      Some(length.min(i32::MAX as usize) as i32),
+      filter.sub_domain.as_deref(),
+    )?;
+    Ok(RecordPage {
+      records,
+      total: None,
+    })
+  }
+
   fn modify_record(
     &self,
-    id: u64,
+    id: &str,
     sub_domain: Option<&str>,
     r_type: &str,
     r_line: &str,
     value: &str,
-  ) -> Result<u64, Error>;
+    spec: &RecordSpec,
+  ) -> Result<String, Error>;
+
+  fn delete_record(&self, id: &str) -> Result<(), Error>;
+
+  /// Action name and payload of the request making the change, logged by
+  /// the dry run. The default is the method name with its arguments.
+  fn change_request(&self, change: &Change) -> Result<(String, Value), Error> {
+    Ok((String::from(change.name()), serde_json::to_value(change)?))
+  }
+
+  /// Enable or disable a record, keeping its id.
+  fn set_record_status(
+    &self,
+    _id: &str,
+    _status: RecordStatus,
+  ) -> Result<(), Error> {
+    Err(Error::unsupported("Setting the record status"))
+  }
+
+  /// Whether `set_record_status` is implemented, records being disabled
+  /// only then.
+  fn supports_status(&self) -> bool {
+    false
+  }
 
-  fn delete_record(&self, id: u64) -> Result<(), Error>;
+  /// Fields of `RecordSpec` the provider takes, the others are ignored with
+  /// a warning.
+  fn spec_fields(&self) -> &'static [&'static str];
+
+  /// Name on DNSPod of the line `record_line` of the provider, none when
+  /// DNSPod has no such line.
+  ///
+  /// Lines are translated between providers by their names on DNSPod, the
+  /// default line being `COMMON_LINE_DEFAULT`.
+  fn common_line(&self, record_line: &str) -> Option<String>;
+
+  /// Line of the provider, as listed, for the line named `common_line` on
+  /// DNSPod, none when the provider has no such line.
+  fn provider_line(&self, common_line: &str) -> Option<String>;
+
+  /// Name on DNSPod of the type `record_type` of the provider, none when
+  /// DNSPod has no such type. The default is the type itself.
+  fn common_type(&self, record_type: &str) -> Option<String> {
+    Some(record_type.to_uppercase())
+  }
+
+  /// Type of the provider for the type named `common_type` on DNSPod, none
+  /// when the provider has no such type. The default is the type itself.
+  fn provider_type(&self, common_type: &str) -> Option<String> {
+    Some(common_type.to_uppercase())
+  }
+}
+
+/// Iterator over the pages of records, until the total count is reached
+/// or a page is short. Records of the pages are those matching the filter.
+pub struct RecordPages<'a> {
+  provider: &'a dyn DnsProvider,
+  filter: &'a RecordFilter,
+  page_size: usize,
+  offset: usize,
+  done: bool,
+}
+
+impl<'a> RecordPages<'a> {
+  pub fn new(
+    provider: &'a dyn DnsProvider,
+    filter: &'a RecordFilter,
+    page_size: usize,
+  ) -> Self {
+    RecordPages {
+      provider,
+      filter,
+      page_size: page_size.max(1),
+      offset: 0,
+      done: false,
+    }
+  }
+}
+
+impl Iterator for RecordPages<'_> {
+  type Item = Result<RecordPage, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let mut page = match self.provider.list_record_page(
+      self.offset,
+      self.page_size,
+      self.filter,
+    ) {
+      Ok(page) => page,
+      Err(err) => {
+        self.done = true;
+        return Some(Err(err));
+      }
+    };
+    self.offset += page.records.len();
+    self.done = page.records.len() < self.page_size
+      || page.total.is_some_and(|total| self.offset >= total);
+    if page.records.is_empty() {
+      return None;
+    }
+    page.records.retain(|record| self.filter.matches(record));
+    Some(Ok(page))
+  }
+}
+
+/// Every record matching the filter, with their count.
+pub fn list_all(
+  provider: &dyn DnsProvider,
+  filter: &RecordFilter,
+) -> Result<RecordPage, Error> {
+  let mut records = Vec::new();
+  for page in RecordPages::new(provider, filter, LIST_PAGE_SIZE) {
+    records.extend(page?.records);
+  }
+  Ok(RecordPage {
+    total: Some(records.len()),
+    records,
+  })
+}
+
+/// The record of `id`, searched among the records of `sub_domain` when
+/// given.
+pub fn find_record(
+  provider: &dyn DnsProvider,
+  id: &str,
+  sub_domain: Option<&str>,
+) -> Result<Option<Record>, Error> {
+  let filter = RecordFilter::sub_domain(sub_domain);
+  let records = list_all(provider, &filter)?.records;
+  Ok(records.into_iter().find(|record| record.id == id))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::memory;
+
+  #[test]
+  fn test_list_all() {
+    let provider = memory::Provider::default();
+    for i in 0..250 {
+      let sub_domain = if i % 5 == 0 { "www" } else { "home" };
+      provider
+        .add_record(sub_domain, "A", "默认", "1.2.3.4", &RecordSpec::default())
+        .unwrap();
+    }
+
+    let filter = RecordFilter::default();
+    let pages: Vec<usize> =
+      RecordPages::new(&provider, &filter, LIST_PAGE_SIZE)
+        .map(|page| page.unwrap().records.len())
+        .collect();
+    assert_eq!(pages, vec![100, 100, 50]);
+
+    let filter = RecordFilter::sub_domain(Some("www"));
+    let all = list_all(&provider, &filter).unwrap();
+    assert_eq!(all.total, Some(50));
+    assert_eq!(all.records.len(), 50);
+    assert_eq!(all.records[49].id, "246");
+
+    // a full last page is known to be the last by the total count
+    let pages = RecordPages::new(&provider, &filter, 25).count();
+    assert_eq!(pages, 2);
+  }
+
+  #[test]
+  fn test_record_filter() {
+    let record = |value: &str, status| Record {
+      id: String::from("1"),
+      sub_domain: String::from("www"),
+      value: String::from(value),
+      r_type: String::from("A"),
+      r_line: String::from("默认"),
+      line_id: None,
+      ttl: None,
+      mx: None,
+      weight: None,
+      status,
+      remark: None,
+    };
+    let internal = record("10.1.2.3", Some(RecordStatus::Enabled));
+    let public = record("1.2.3.4", None);
+    let disabled = record("10.3.2.1", Some(RecordStatus::Disabled));
+
+    let filter = RecordFilter {
+      record_type: Some(String::from("a")),
+      value: Some(ValueMatch::Network("10.0.0.0/8".parse().unwrap())),
+      ..RecordFilter::default()
+    };
+    assert!(filter.matches(&internal));
+    assert!(!filter.matches(&public));
+    assert!(filter.matches(&disabled));
+
+    let filter = RecordFilter {
+      status: Some(RecordStatus::Enabled),
+      keyword: Some(String::from("2.3")),
+      ..RecordFilter::default()
+    };
+    assert!(filter.matches(&internal));
+    assert!(filter.matches(&public));
+    assert!(!filter.matches(&disabled));
+
+    let filter = RecordFilter {
+      record_line: Some(String::from("电信")),
+      value: Some(ValueMatch::Contains(String::from("1.2"))),
+      ..RecordFilter::default()
+    };
+    assert!(!filter.matches(&internal));
+
+    // lines are matched by name or by id, and no line is empty
+    let telecom = Record {
+      r_line: String::from("电信"),
+      line_id: Some(String::from("10=0")),
+      ..public.clone()
+    };
+    let line = |r_line: &str| RecordFilter {
+      record_line: Some(String::from(r_line)),
+      ..RecordFilter::default()
+    };
+    assert!(line("10=0").matches(&telecom));
+    assert!(line("电信").matches(&telecom));
+    assert!(!line("0").matches(&telecom));
+    assert!(!line("").matches(&telecom));
+    assert!("paused".parse::<RecordStatus>().is_err());
+  }
 }
@@ -0,0 +1,227 @@
+//! In-memory provider used by the unit tests.
+
+use std::cell::RefCell;
+
+use super::interface::*;
+use crate::error::{Error, Result};
+
+/// Provider with the lines of DNSPod, or with only a default line named
+/// `default` as Cloudflare and RFC 2136 have.
+#[derive(Default)]
+pub struct Provider {
+  pub records: RefCell<Vec<Record>>,
+  next_id: RefCell<u64>,
+  no_lines: bool,
+}
+
+/// Id of the default line.
+const LINE_ID_DEFAULT: &str = "0";
+/// Name of the default line without the lines of DNSPod.
+const NO_LINES_DEFAULT: &str = "default";
+
+/// Record of the tests, on the default line of DNSPod with a TTL of 600.
+pub fn record(id: &str, sub_domain: &str, r_type: &str, value: &str) -> Record {
+  Record {
+    id: String::from(id),
+    sub_domain: String::from(sub_domain),
+    value: String::from(value),
+    r_type: String::from(r_type),
+    r_line: String::from(COMMON_LINE_DEFAULT),
+    line_id: Some(String::from(LINE_ID_DEFAULT)),
+    ttl: Some(600),
+    mx: None,
+    weight: None,
+    status: None,
+    remark: None,
+  }
+}
+
+impl Provider {
+  pub fn with_records(records: Vec<Record>) -> Self {
+    let next_id = records
+      .iter()
+      .filter_map(|r| r.id.parse().ok())
+      .max()
+      .unwrap_or(0);
+    Provider {
+      records: RefCell::new(records),
+      next_id: RefCell::new(next_id),
+      no_lines: false,
+    }
+  }
+
+  /// Provider with only a default line and no status, as Cloudflare.
+  pub fn without_lines(records: Vec<Record>) -> Self {
+    Provider {
+      no_lines: true,
+      ..Provider::with_records(records)
+    }
+  }
+
+  /// Name and id of `record_line`, given by name or by id.
+  fn line(&self, record_line: &str) -> (String, Option<String>) {
+    let default = if self.no_lines {
+      NO_LINES_DEFAULT
+    } else {
+      COMMON_LINE_DEFAULT
+    };
+    if self.no_lines || [default, LINE_ID_DEFAULT].contains(&record_line) {
+      (String::from(default), Some(String::from(LINE_ID_DEFAULT)))
+    } else {
+      (String::from(record_line), None)
+    }
+  }
+}
+
+impl DnsProvider for Provider {
+  fn add_record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let mut next_id = self.next_id.borrow_mut();
+    *next_id += 1;
+    let id = next_id.to_string();
+    let (r_line, line_id) = self.line(record_line);
+    self.records.borrow_mut().push(Record {
+      id: id.clone(),
+      sub_domain: String::from(sub_domain),
+      value: String::from(value),
+      r_type: String::from(record_type),
+      r_line,
+      line_id,
+      ttl: spec.ttl,
+      mx: spec.mx,
+      weight: spec.weight,
+      status: spec.status,
+      remark: spec.remark.clone(),
+    });
+    Ok(id)
+  }
+
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    let records = self.records.borrow();
+    Ok(
+      records
+        .iter()
+        .filter(|r| {
+          sub_domain.is_none() || sub_domain == Some(r.sub_domain.as_str())
+        })
+        .skip(offset.unwrap_or(0) as usize)
+        .take(length.unwrap_or(i32::MAX) as usize)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
+    let sub_domain = filter.sub_domain.as_deref();
+    let total = self
+      .records
+      .borrow()
+      .iter()
+      .filter(|r| {
+        sub_domain.is_none() || sub_domain == Some(r.sub_domain.as_str())
+      })
+      .count();
+    let records =
+      self.list_record(Some(offset as i32), Some(length as i32), sub_domain)?;
+    Ok(RecordPage {
+      records,
+      total: Some(total),
+    })
+  }
+
+  fn modify_record(
+    &self,
+    id: &str,
+    sub_domain: Option<&str>,
+    r_type: &str,
+    r_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    let mut records = self.records.borrow_mut();
+    let record = records
+      .iter_mut()
+      .find(|r| r.id == id)
+      .ok_or_else(|| Error::Reason(format!("No such record: {}", id)))?;
+    if let Some(sub_domain) = sub_domain {
+      record.sub_domain = String::from(sub_domain);
+    }
+    record.r_type = String::from(r_type);
+    (record.r_line, record.line_id) = self.line(r_line);
+    record.value = String::from(value);
+    record.ttl = spec.ttl.or(record.ttl);
+    record.mx = spec.mx.or(record.mx);
+    record.weight = spec.weight.or(record.weight);
+    record.status = spec.status.or(record.status);
+    if let Some(remark) = &spec.remark {
+      record.remark = Some(remark.clone());
+    }
+    Ok(String::from(id))
+  }
+
+  fn delete_record(&self, id: &str) -> Result<()> {
+    self.records.borrow_mut().retain(|r| r.id != id);
+    Ok(())
+  }
+
+  fn set_record_status(&self, id: &str, status: RecordStatus) -> Result<()> {
+    if !self.supports_status() {
+      return Err(Error::unsupported("Setting the record status"));
+    }
+    let mut records = self.records.borrow_mut();
+    let record = records
+      .iter_mut()
+      .find(|r| r.id == id)
+      .ok_or_else(|| Error::Reason(format!("No such record: {}", id)))?;
+    record.status = Some(status);
+    Ok(())
+  }
+
+  fn supports_status(&self) -> bool {
+    !self.no_lines
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    if self.no_lines {
+      &["ttl", "mx"]
+    } else {
+      SPEC_FIELDS
+    }
+  }
+
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    if !self.no_lines {
+      Some(String::from(record_line))
+    } else if record_line == NO_LINES_DEFAULT {
+      Some(String::from(COMMON_LINE_DEFAULT))
+    } else {
+      None
+    }
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    if !self.no_lines {
+      Some(String::from(common_line))
+    } else if common_line == COMMON_LINE_DEFAULT {
+      Some(String::from(NO_LINES_DEFAULT))
+    } else {
+      None
+    }
+  }
+}
@@ -0,0 +1,662 @@
+//! Dynamic update (RFC 2136) of an authoritative server, signed with TSIG
+//! (RFC 8945).
+//!
+//! The key file uses the `nsupdate -y` format, `[hmac-sha256:]name:secret`
+//! where the secret is base64 encoded, and the domain names the server as
+//! `zone@server[:port]`.
+//!
+//! Responses to updates are verified with the key, the messages of a zone
+//! transfer are not.
+//!
+//! Records have no id on a plain DNS server, so the id of a record is made
+//! of its sub-domain, type and value as `sub/TYPE/value`.
+
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use trust_dns_proto::op::{
+  Message, MessageType, OpCode, Query, ResponseCode, UpdateMessage,
+};
+use trust_dns_proto::rr::dnssec::rdata::tsig::{
+  make_tsig_record, message_tbs, signed_bitmessage_to_buf, TsigAlgorithm, TSIG,
+};
+use trust_dns_proto::rr::dnssec::rdata::DNSSECRData;
+use trust_dns_proto::rr::{self, DNSClass, Name, RData, RecordType};
+use trust_dns_proto::serialize::txt::RDataParser;
+
+use super::interface::*;
+use crate::error::{Error, Result};
+
+mod intra_common {
+  use std::time::Duration;
+
+  pub const DNS_PORT: u16 = 53;
+  /// Largest message sent over UDP.
+  pub const DNS_UDP_SIZE: usize = 512;
+  pub const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+  /// TTL of added records.
+  pub const DNS_TTL: u32 = 600;
+
+  pub const TSIG_FUDGE: u16 = 300;
+
+  /// Record line reported for every record.
+  pub const LINE_DEFAULT: &str = "default";
+  /// Id of the line reported for every record.
+  pub const LINE_ID_DEFAULT: &str = "0";
+
+  /// Fields of the spec taken by the provider.
+  pub const SPEC_FIELDS: &[&str] = &["ttl", "mx"];
+}
+
+fn unix_time() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs())
+}
+
+/// TSIG key using hmac-sha256.
+struct TsigKey {
+  name: Name,
+  secret: Vec<u8>,
+}
+
+impl TsigKey {
+  fn parse(key: &str) -> Result<Self> {
+    let parts: Vec<&str> = key.trim().split(':').collect();
+    let (name, secret) = match parts.as_slice() {
+      [name, secret] => (name, secret),
+      [algorithm, name, secret]
+        if algorithm.eq_ignore_ascii_case("hmac-sha256") =>
+      {
+        (name, secret)
+      }
+      _ => {
+        return Err(Error::Reason(String::from(
+          "TSIG key should be [hmac-sha256:]name:secret",
+        )))
+      }
+    };
+    let secret = base64::engine::general_purpose::STANDARD
+      .decode(secret)
+      .map_err(|err| Error::Reason(format!("Bad TSIG secret: {}", err)))?;
+    Ok(TsigKey {
+      name: Name::from_str(name)?,
+      secret,
+    })
+  }
+
+  fn hmac(&self, data: &[u8]) -> Result<Hmac<Sha256>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)?;
+    mac.update(data);
+    Ok(mac)
+  }
+
+  /// Sign `message`, `previous` is the MAC of the request when signing a
+  /// response. Returns the MAC of the message.
+  fn sign(
+    &self,
+    message: &mut Message,
+    previous: Option<&[u8]>,
+  ) -> Result<Vec<u8>> {
+    let tsig = TSIG::new(
+      TsigAlgorithm::HmacSha256,
+      unix_time(),
+      intra_common::TSIG_FUDGE,
+      Vec::new(),
+      message.id(),
+      0,
+      Vec::new(),
+    );
+    let tbs = message_tbs(previous, message, &tsig, &self.name)?;
+    let mac = self.hmac(&tbs)?.finalize().into_bytes().to_vec();
+    message.add_tsig(make_tsig_record(
+      self.name.clone(),
+      tsig.set_mac(mac.clone()),
+    ));
+    Ok(mac)
+  }
+
+  /// Verify the TSIG record of a received message, `previous` is the MAC of
+  /// the request when verifying a response. Returns the MAC of the message.
+  fn verify(&self, message: &[u8], previous: Option<&[u8]>) -> Result<Vec<u8>> {
+    let (tbv, record) = signed_bitmessage_to_buf(previous, message, true)?;
+    let tsig = match record.data() {
+      Some(RData::DNSSEC(DNSSECRData::TSIG(tsig)))
+        if record.name() == &self.name =>
+      {
+        tsig
+      }
+      _ => return Err(Error::Reason(String::from("Unknown TSIG key"))),
+    };
+    self
+      .hmac(&tbv)?
+      .verify_slice(tsig.mac())
+      .map_err(|_| Error::Reason(String::from("Bad TSIG signature")))?;
+    if unix_time().abs_diff(tsig.time()) > u64::from(tsig.fudge()) {
+      return Err(Error::Reason(String::from("Bad TSIG time")));
+    }
+    Ok(tsig.mac().to_vec())
+  }
+}
+
+/// Parse `zone@server[:port]`.
+fn parse_domain(domain: &str) -> Result<(Name, SocketAddr)> {
+  let (zone, server) = domain.rsplit_once('@').ok_or_else(|| {
+    Error::Reason(format!(
+      "Domain should be zone@server[:port], got {}",
+      domain
+    ))
+  })?;
+  let mut zone = Name::from_str(zone)?;
+  zone.set_fqdn(true);
+
+  let server = server.trim_start_matches('[').trim_end_matches(']');
+  let server = match server.parse::<SocketAddr>() {
+    Ok(addr) => addr,
+    Err(_) => (server, intra_common::DNS_PORT)
+      .to_socket_addrs()
+      .or_else(|_| server.to_socket_addrs())?
+      .next()
+      .ok_or_else(|| Error::Reason(format!("No such server: {}", server)))?,
+  };
+  Ok((zone, server))
+}
+
+/// Random id of a message, responses being matched on it.
+fn message_id() -> u16 {
+  rand::random()
+}
+
+fn read_tcp_message(stream: &mut TcpStream) -> Result<Vec<u8>> {
+  let mut length = [0; 2];
+  stream.read_exact(&mut length)?;
+  let mut buffer = vec![0; u16::from_be_bytes(length) as usize];
+  stream.read_exact(&mut buffer)?;
+  Ok(buffer)
+}
+
+fn write_tcp_message(stream: &mut TcpStream, message: &[u8]) -> Result<()> {
+  stream.write_all(&(message.len() as u16).to_be_bytes())?;
+  stream.write_all(message)?;
+  Ok(())
+}
+
+pub struct Provider {
+  zone: Name,
+  server: SocketAddr,
+  key: TsigKey,
+  /// Records of the last zone transfer of a listing with their sub-domain
+  /// filter, the pages after the first one being taken from them.
+  listed: RefCell<Option<(Option<String>, Vec<Record>)>>,
+}
+
+impl Provider {
+  fn name(&self, sub_domain: &str) -> Result<Name> {
+    if sub_domain == "@" || sub_domain.is_empty() {
+      Ok(self.zone.clone())
+    } else {
+      Ok(Name::from_str(sub_domain)?.append_domain(&self.zone)?)
+    }
+  }
+
+  fn sub_domain(&self, name: &Name) -> String {
+    let name = name.to_lowercase().to_ascii();
+    let zone = self.zone.to_lowercase().to_ascii();
+    match name.strip_suffix(&zone) {
+      Some("") => String::from("@"),
+      Some(sub) => String::from(sub.trim_end_matches('.')),
+      None => name,
+    }
+  }
+
+  fn record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<rr::Record> {
+    let record_type = RecordType::from_str(&record_type.to_uppercase())?;
+    // the priority is part of the MX data, unless already in the value
+    let value = match spec.mx {
+      Some(mx) if record_type == RecordType::MX && !value.contains(' ') => {
+        format!("{} {}", mx, value)
+      }
+      _ => String::from(value),
+    };
+    let rdata = RData::try_from_str(record_type, &value)?;
+    Ok(rr::Record::from_rdata(
+      self.name(sub_domain)?,
+      spec.ttl.unwrap_or(intra_common::DNS_TTL),
+      rdata,
+    ))
+  }
+
+  /// Record of the id, see the module documentation.
+  fn record_of_id(&self, id: &str) -> Result<rr::Record> {
+    let mut parts = id.splitn(3, '/');
+    match (parts.next(), parts.next(), parts.next()) {
+      (Some(sub_domain), Some(record_type), Some(value)) => {
+        self.record(sub_domain, record_type, value, &RecordSpec::default())
+      }
+      _ => Err(Error::Reason(format!("Bad record id: {}", id))),
+    }
+  }
+
+  fn record_parse(&self, record: &rr::Record) -> Option<Record> {
+    let sub_domain = self.sub_domain(record.name());
+    let r_type = record.record_type().to_string();
+    let rdata = record.data()?;
+    let mx = match rdata {
+      RData::MX(mx) => Some(mx.preference()),
+      _ => None,
+    };
+    let value = rdata.to_string();
+    Some(Record {
+      id: format!("{}/{}/{}", sub_domain, r_type, value),
+      sub_domain,
+      value,
+      r_type,
+      r_line: String::from(intra_common::LINE_DEFAULT),
+      line_id: Some(String::from(intra_common::LINE_ID_DEFAULT)),
+      ttl: Some(record.ttl()),
+      mx,
+      weight: None,
+      status: None,
+      remark: None,
+    })
+  }
+
+  /// Send an update with the given update section.
+  fn update(&self, updates: Vec<rr::Record>) -> Result<()> {
+    let mut zone = Query::query(self.zone.clone(), RecordType::SOA);
+    zone.set_query_class(DNSClass::IN);
+
+    let mut message = Message::new();
+    message
+      .set_id(message_id())
+      .set_message_type(MessageType::Query)
+      .set_op_code(OpCode::Update);
+    message.add_zone(zone);
+    for update in updates {
+      message.add_update(update);
+    }
+    tracing::debug!("UPDATE: {:?}", message);
+
+    let mac = self.key.sign(&mut message, None)?;
+    let response = self.exchange(&message.to_vec()?)?;
+    let parsed = Message::from_vec(&response)?;
+    tracing::debug!("RESPONSE: {:?}", parsed);
+
+    if parsed.id() != message.id() {
+      Err(Error::Reason(String::from("Mismatched response id")))
+    } else if parsed.response_code() != ResponseCode::NoError {
+      // errors about the key itself are not signed
+      Err(Error::Reason(format!(
+        "Update failed: {}",
+        parsed.response_code()
+      )))
+    } else {
+      self.key.verify(&response, Some(&mac))?;
+      Ok(())
+    }
+  }
+
+  /// Send a message over UDP, falling back to TCP for large or truncated
+  /// messages.
+  fn exchange(&self, request: &[u8]) -> Result<Vec<u8>> {
+    if request.len() <= intra_common::DNS_UDP_SIZE {
+      let bind: SocketAddr = if self.server.is_ipv4() {
+        "0.0.0.0:0".parse()?
+      } else {
+        "[::]:0".parse()?
+      };
+      let socket = UdpSocket::bind(bind)?;
+      socket.set_read_timeout(Some(intra_common::DNS_TIMEOUT))?;
+      socket.connect(self.server)?;
+      socket.send(request)?;
+      let mut buffer = vec![0; 65535];
+      let length = socket.recv(&mut buffer)?;
+      buffer.truncate(length);
+      // TC bit
+      if buffer.len() < 3 || buffer[2] & 0x02 == 0 {
+        return Ok(buffer);
+      }
+    }
+
+    let mut stream = self.connect()?;
+    write_tcp_message(&mut stream, request)?;
+    read_tcp_message(&mut stream)
+  }
+
+  fn connect(&self) -> Result<TcpStream> {
+    let stream =
+      TcpStream::connect_timeout(&self.server, intra_common::DNS_TIMEOUT)?;
+    stream.set_read_timeout(Some(intra_common::DNS_TIMEOUT))?;
+    Ok(stream)
+  }
+
+  /// Records of the zone, of `sub_domain` when given.
+  fn records(&self, sub_domain: Option<&str>) -> Result<Vec<Record>> {
+    let name = sub_domain.map(|s| self.name(s)).transpose()?;
+    Ok(
+      self
+        .transfer()?
+        .iter()
+        .filter(|r| name.is_none() || name.as_ref() == Some(r.name()))
+        .filter_map(|r| self.record_parse(r))
+        .collect(),
+    )
+  }
+
+  /// Transfer the zone with AXFR.
+  fn transfer(&self) -> Result<Vec<rr::Record>> {
+    let mut query = Query::query(self.zone.clone(), RecordType::AXFR);
+    query.set_query_class(DNSClass::IN);
+    let mut message = Message::new();
+    message
+      .set_id(message_id())
+      .set_message_type(MessageType::Query)
+      .set_op_code(OpCode::Query);
+    message.add_query(query);
+
+    self.key.sign(&mut message, None)?;
+    let mut stream = self.connect()?;
+    write_tcp_message(&mut stream, &message.to_vec()?)?;
+
+    // The transfer starts and ends with the SOA record.
+    let mut records = Vec::new();
+    let mut soa = 0;
+    while soa < 2 {
+      let response = Message::from_vec(&read_tcp_message(&mut stream)?)?;
+      if response.response_code() != ResponseCode::NoError {
+        return Err(Error::Reason(format!(
+          "Zone transfer failed: {}",
+          response.response_code()
+        )));
+      }
+      if response.answers().is_empty() {
+        return Err(Error::Reason(String::from("Incomplete zone transfer")));
+      }
+      for record in response.answers() {
+        if record.record_type() == RecordType::SOA {
+          soa += 1;
+        } else {
+          records.push(record.clone());
+        }
+      }
+    }
+    Ok(records)
+  }
+}
+
+impl DnsProviderBuild for Provider {
+  fn build_provider(key: String, domain: String) -> Result<Self> {
+    let (zone, server) = parse_domain(&domain)?;
+    Ok(Provider {
+      zone,
+      server,
+      key: TsigKey::parse(&key)?,
+      listed: RefCell::new(None),
+    })
+  }
+}
+
+impl DnsProvider for Provider {
+  fn add_record(
+    &self,
+    sub_domain: &str,
+    record_type: &str,
+    _record_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    spec.warn_unsupported("rfc2136", intra_common::SPEC_FIELDS);
+    let record = self.record(sub_domain, record_type, value, spec)?;
+    let id = self.record_parse(&record).map(|r| r.id);
+    self.update(vec![record])?;
+    id.ok_or_else(|| Error::Reason(String::from("Empty record")))
+  }
+
+  fn list_record(
+    &self,
+    offset: Option<i32>,
+    length: Option<i32>,
+    sub_domain: Option<&str>,
+  ) -> Result<Vec<Record>> {
+    Ok(
+      self
+        .records(sub_domain)?
+        .into_iter()
+        .skip(offset.unwrap_or(0).max(0) as usize)
+        .take(length.map_or(usize::MAX, |l| l.max(0) as usize))
+        .collect(),
+    )
+  }
+
+  /// The zone is transferred for the first page only, the next pages are
+  /// taken from the same transfer.
+  fn list_record_page(
+    &self,
+    offset: usize,
+    length: usize,
+    filter: &RecordFilter,
+  ) -> Result<RecordPage> {
+    let mut listed = self.listed.borrow_mut();
+    let cached = match listed.as_ref() {
+      Some((sub_domain, _)) => offset > 0 && *sub_domain == filter.sub_domain,
+      None => false,
+    };
+    if !cached {
+      let records = self.records(filter.sub_domain.as_deref())?;
+      *listed = Some((filter.sub_domain.clone(), records));
+    }
+    let records = listed.as_ref().map_or(&[][..], |(_, r)| r.as_slice());
+    Ok(RecordPage {
+      records: records.iter().skip(offset).take(length).cloned().collect(),
+      total: Some(records.len()),
+    })
+  }
+
+  fn modify_record(
+    &self,
+    id: &str,
+    sub_domain: Option<&str>,
+    r_type: &str,
+    _r_line: &str,
+    value: &str,
+    spec: &RecordSpec,
+  ) -> Result<String> {
+    spec.warn_unsupported("rfc2136", intra_common::SPEC_FIELDS);
+    let mut old = self.record_of_id(id)?;
+    let sub_domain = match sub_domain {
+      Some(sub_domain) => String::from(sub_domain),
+      None => self.sub_domain(old.name()),
+    };
+    let new = self.record(&sub_domain, r_type, value, spec)?;
+    let new_id = self.record_parse(&new).map(|r| r.id);
+
+    // delete the old RR and add the new one in one update
+    old.set_dns_class(DNSClass::NONE).set_ttl(0);
+    self.update(vec![old, new])?;
+    new_id.ok_or_else(|| Error::Reason(String::from("Empty record")))
+  }
+
+  fn delete_record(&self, id: &str) -> Result<()> {
+    let mut record = self.record_of_id(id)?;
+    record.set_dns_class(DNSClass::NONE).set_ttl(0);
+    self.update(vec![record])
+  }
+
+  fn spec_fields(&self) -> &'static [&'static str] {
+    intra_common::SPEC_FIELDS
+  }
+
+  fn common_line(&self, record_line: &str) -> Option<String> {
+    Some(String::from(COMMON_LINE_DEFAULT))
+      .filter(|_| record_line == intra_common::LINE_DEFAULT)
+  }
+
+  fn provider_line(&self, common_line: &str) -> Option<String> {
+    Some(String::from(intra_common::LINE_DEFAULT))
+      .filter(|_| common_line == COMMON_LINE_DEFAULT)
+  }
+
+  /// Types known to the DNS, URL forwarding types of other providers are
+  /// not.
+  fn provider_type(&self, common_type: &str) -> Option<String> {
+    RecordType::from_str(&common_type.to_uppercase())
+      .ok()
+      .map(|r_type| r_type.to_string())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::net::TcpListener;
+  use std::thread;
+
+  const KEY: &str = "hmac-sha256:cao-key:c2VjcmV0LWtleS1mb3ItdGVzdHM=";
+
+  fn record(name: &str, rdata: RData) -> rr::Record {
+    rr::Record::from_rdata(Name::from_str(name).unwrap(), 300, rdata)
+  }
+
+  #[test]
+  fn test_tsig_sign() {
+    let key = TsigKey::parse(KEY).unwrap();
+    let mut message = Message::new();
+    message.set_id(0x1234);
+    let mac = key.sign(&mut message, None).unwrap();
+    let mut request = message.to_vec().unwrap();
+    assert_eq!(key.verify(&request, None).unwrap(), mac);
+
+    let other = TsigKey::parse("cao-key:b3RoZXI=").unwrap();
+    assert!(other.verify(&request, None).is_err());
+
+    // tamper with the flags
+    request[2] ^= 1;
+    assert!(key.verify(&request, None).is_err());
+  }
+
+  #[test]
+  fn test_rfc2136_record_actions() {
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = udp.local_addr().unwrap();
+    let tcp = TcpListener::bind(addr).unwrap();
+
+    // stand-in server answering two updates and two zone transfers
+    let server = thread::spawn(move || {
+      let key = TsigKey::parse(KEY).unwrap();
+      let mut updates = Vec::new();
+      for _ in 0..2 {
+        let mut buffer = vec![0; 4096];
+        let (length, peer) = udp.recv_from(&mut buffer).unwrap();
+        let mac = key.verify(&buffer[..length], None).unwrap();
+        let request = Message::from_vec(&buffer[..length]).unwrap();
+        assert_eq!(request.op_code(), OpCode::Update);
+
+        let mut response = Message::new();
+        response
+          .set_id(request.id())
+          .set_message_type(MessageType::Response)
+          .set_op_code(OpCode::Update);
+        key.sign(&mut response, Some(&mac)).unwrap();
+        udp.send_to(&response.to_vec().unwrap(), peer).unwrap();
+        updates.push(request.updates().to_vec());
+      }
+
+      for _ in 0..2 {
+        let (mut stream, _) = tcp.accept().unwrap();
+        let request = read_tcp_message(&mut stream).unwrap();
+        key.verify(&request, None).unwrap();
+        let request = Message::from_vec(&request).unwrap();
+        assert_eq!(request.queries()[0].query_type(), RecordType::AXFR);
+        let soa = record(
+          "example.com.",
+          RData::try_from_str(
+            RecordType::SOA,
+            "ns.example.com. admin.example.com. 1 3600 600 86400 300",
+          )
+          .unwrap(),
+        );
+        let mut response = Message::new();
+        response
+          .set_id(request.id())
+          .set_message_type(MessageType::Response)
+          .add_answer(soa.clone())
+          .add_answer(record(
+            "www.example.com.",
+            RData::try_from_str(RecordType::A, "1.2.3.4").unwrap(),
+          ))
+          .add_answer(record(
+            "example.com.",
+            RData::try_from_str(RecordType::A, "5.6.7.8").unwrap(),
+          ))
+          .add_answer(soa);
+        write_tcp_message(&mut stream, &response.to_vec().unwrap()).unwrap();
+      }
+      updates
+    });
+
+    let provider = Provider::build_provider(
+      String::from(KEY),
+      format!("example.com@{}", addr),
+    )
+    .unwrap();
+
+    let spec = RecordSpec {
+      ttl: Some(60),
+      ..RecordSpec::default()
+    };
+    let id = provider
+      .add_record("www", "A", "default", "1.2.3.4", &spec)
+      .unwrap();
+    assert_eq!(id, "www/A/1.2.3.4");
+    let id = provider
+      .modify_record(&id, None, "A", "", "2.3.4.5", &RecordSpec::default())
+      .unwrap();
+    assert_eq!(id, "www/A/2.3.4.5");
+
+    let records = provider.list_record(None, None, Some("www")).unwrap();
+    assert_eq!(
+      records,
+      vec![Record {
+        id: String::from("www/A/1.2.3.4"),
+        sub_domain: String::from("www"),
+        value: String::from("1.2.3.4"),
+        r_type: String::from("A"),
+        r_line: String::from("default"),
+        line_id: Some(String::from("0")),
+        ttl: Some(300),
+        mx: None,
+        weight: None,
+        status: None,
+        remark: None,
+      }]
+    );
+
+    // a single transfer for every page
+    let filter = RecordFilter::default();
+    let pages: Vec<RecordPage> = RecordPages::new(&provider, &filter, 1)
+      .collect::<Result<_>>()
+      .unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[1].records[0].id, "@/A/5.6.7.8");
+
+    let updates = server.join().unwrap();
+    assert_eq!(updates[0].len(), 1);
+    assert_eq!(updates[0][0].name().to_ascii(), "www.example.com.");
+    assert_eq!(updates[0][0].ttl(), 60);
+    assert_eq!(updates[1].len(), 2);
+    assert_eq!(updates[1][0].dns_class(), DNSClass::NONE);
+    assert_eq!(updates[1][1].data().unwrap().to_string(), "2.3.4.5");
+  }
+}
@@ -0,0 +1,92 @@
+//! Public address discovery, for hosts behind NAT where the interface only
+//! holds a private address.
+//!
+//! A source is given as `SCHEME[:ARGUMENTS]`, e.g. `http` or
+//! `http:https://api.ipify.org`, `stun:stun.example.com:3478` or
+//! `dns:google`, or `upnp` to ask the router.
+
+use std::net::IpAddr;
+
+use crate::error::{Error, Result};
+
+mod dns;
+mod http;
+mod stun;
+mod upnp;
+
+/// Address family held by a record.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+  V4,
+  V6,
+}
+
+impl Family {
+  /// `AAAA` records hold IPv6 addresses, anything else IPv4.
+  pub fn of_record(record_type: &str) -> Self {
+    if record_type.eq_ignore_ascii_case("AAAA") {
+      Family::V6
+    } else {
+      Family::V4
+    }
+  }
+
+  pub fn matches(self, ip: &IpAddr) -> bool {
+    match self {
+      Family::V4 => ip.is_ipv4(),
+      Family::V6 => ip.is_ipv6(),
+    }
+  }
+}
+
+impl std::fmt::Display for Family {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Family::V4 => write!(f, "IPv4"),
+      Family::V6 => write!(f, "IPv6"),
+    }
+  }
+}
+
+/// Discover the public address of `family` with the source `spec`.
+pub fn source_ip(spec: &str, family: Family) -> Result<IpAddr> {
+  let (scheme, arguments) = match spec.split_once(':') {
+    Some((scheme, arguments)) => (scheme, Some(arguments)),
+    None => (spec, None),
+  };
+  match scheme.trim() {
+    "dns" => dns::public_ip(arguments, family),
+    "http" => http::public_ip(arguments, family),
+    "stun" => stun::public_ip(arguments, family),
+    "upnp" => upnp::public_ip(arguments, family),
+    _ => Err(Error::Reason(format!("Unknown address source: {}", spec))),
+  }
+}
+
+/// Parse an address reported by a source and check its family.
+fn parse_reply(reply: &str, family: Family) -> Result<IpAddr> {
+  let ip: IpAddr = reply.trim().parse().map_err(|_| {
+    Error::Reason(format!("Not an address: {:?}", reply.trim()))
+  })?;
+  if family.matches(&ip) {
+    Ok(ip)
+  } else {
+    Err(Error::Reason(format!("Not an {} address: {}", family, ip)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_parse_reply() {
+    assert_eq!(
+      parse_reply("1.2.3.4\n", Family::V4).unwrap(),
+      "1.2.3.4".parse::<IpAddr>().unwrap()
+    );
+    assert!(parse_reply("1.2.3.4", Family::V6).is_err());
+    assert!(parse_reply("<html>", Family::V4).is_err());
+    assert!(source_ip("ftp:example.com", Family::V4).is_err());
+  }
+}
@@ -0,0 +1,208 @@
+//! Public address answered by DNS services, for networks where HTTP
+//! services are blocked.
+//!
+//! The argument is `[opendns|google][@resolver[:port]]`, OpenDNS being the
+//! default. OpenDNS answers `myip.opendns.com` with the address of the
+//! client, Google answers it as TXT record of `o-o.myaddr.l.google.com`.
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use trust_dns_resolver::config::{
+  NameServerConfigGroup, ResolverConfig, ResolverOpts,
+};
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::Resolver;
+
+use super::{parse_reply, Family};
+use crate::error::{Error, Result};
+
+mod intra_common {
+  use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+  use std::time::Duration;
+
+  pub const DNS_PORT: u16 = 53;
+  pub const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+  pub const OPENDNS_NAME: &str = "myip.opendns.com.";
+  /// resolver1.opendns.com
+  pub const OPENDNS_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(208, 67, 222, 222));
+  pub const OPENDNS_V6: IpAddr =
+    IpAddr::V6(Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35));
+
+  pub const GOOGLE_NAME: &str = "o-o.myaddr.l.google.com.";
+  /// ns1.google.com
+  pub const GOOGLE_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(216, 239, 32, 10));
+  pub const GOOGLE_V6: IpAddr =
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x4860, 0x4802, 0x32, 0, 0, 0, 0xa));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Service {
+  OpenDns,
+  Google,
+}
+
+impl Service {
+  fn resolver(self, family: Family) -> IpAddr {
+    match (self, family) {
+      (Service::OpenDns, Family::V4) => intra_common::OPENDNS_V4,
+      (Service::OpenDns, Family::V6) => intra_common::OPENDNS_V6,
+      (Service::Google, Family::V4) => intra_common::GOOGLE_V4,
+      (Service::Google, Family::V6) => intra_common::GOOGLE_V6,
+    }
+  }
+}
+
+/// Address of the resolver, of `family` when it has one as the answer may
+/// depend on the family of the query.
+fn parse_resolver(resolver: &str, family: Family) -> Result<SocketAddr> {
+  let resolver = resolver.trim();
+  let addrs: Vec<SocketAddr> = match resolver.to_socket_addrs() {
+    Ok(addrs) => addrs.collect(),
+    Err(_) => (
+      resolver.trim_start_matches('[').trim_end_matches(']'),
+      intra_common::DNS_PORT,
+    )
+      .to_socket_addrs()?
+      .collect(),
+  };
+  addrs
+    .iter()
+    .find(|addr| family.matches(&addr.ip()))
+    .or_else(|| addrs.first())
+    .copied()
+    .ok_or_else(|| Error::Reason(format!("No such resolver: {}", resolver)))
+}
+
+fn parse_argument(
+  argument: Option<&str>,
+  family: Family,
+) -> Result<(Service, SocketAddr)> {
+  let argument = argument.unwrap_or("").trim();
+  let (service, resolver) = match argument.split_once('@') {
+    Some((service, resolver)) => (service.trim(), Some(resolver)),
+    None => (argument, None),
+  };
+  let service = match service {
+    "" | "opendns" => Service::OpenDns,
+    "google" => Service::Google,
+    _ => {
+      return Err(Error::Reason(format!("Unknown DNS service: {}", service)))
+    }
+  };
+  let resolver = match resolver {
+    Some(resolver) => parse_resolver(resolver, family)?,
+    None => SocketAddr::new(service.resolver(family), intra_common::DNS_PORT),
+  };
+  Ok((service, resolver))
+}
+
+pub fn public_ip(argument: Option<&str>, family: Family) -> Result<IpAddr> {
+  let (service, resolver) = parse_argument(argument, family)?;
+
+  let config = ResolverConfig::from_parts(
+    None,
+    Vec::new(),
+    NameServerConfigGroup::from_ips_clear(
+      &[resolver.ip()],
+      resolver.port(),
+      true,
+    ),
+  );
+  let mut options = ResolverOpts::default();
+  options.timeout = intra_common::DNS_TIMEOUT;
+  options.cache_size = 0;
+  options.use_hosts_file = false;
+  let client = Resolver::new(config, options)?;
+
+  tracing::debug!("DNS SOURCE: {:?} {}", service, resolver);
+  let answers: Vec<String> = match service {
+    Service::OpenDns => {
+      let record_type = match family {
+        Family::V4 => RecordType::A,
+        Family::V6 => RecordType::AAAA,
+      };
+      client
+        .lookup(intra_common::OPENDNS_NAME, record_type)?
+        .iter()
+        .filter_map(RData::ip_addr)
+        .map(|ip| ip.to_string())
+        .collect()
+    }
+    Service::Google => client
+      .lookup(intra_common::GOOGLE_NAME, RecordType::TXT)?
+      .iter()
+      .filter_map(RData::as_txt)
+      .flat_map(|txt| txt.iter())
+      .map(|data| String::from_utf8_lossy(data).into_owned())
+      .collect(),
+  };
+
+  // Google also answers the subnet of the client resolver as TXT record
+  answers
+    .iter()
+    .find_map(|answer| parse_reply(answer, family).ok())
+    .ok_or_else(|| {
+      Error::Reason(format!(
+        "No {} address in the answer of {}: {:?}",
+        family, resolver, answers
+      ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::net::{Ipv4Addr, Ipv6Addr, UdpSocket};
+  use std::str::FromStr;
+  use std::thread;
+  use trust_dns_resolver::proto::op::{Message, MessageType};
+  use trust_dns_resolver::proto::rr::{rdata, Name, Record};
+
+  /// Answer one query with `rdata`.
+  fn dns_server(rdata: RData) -> (String, thread::JoinHandle<Name>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+      let mut buffer = [0; 1024];
+      let (length, peer) = socket.recv_from(&mut buffer).unwrap();
+      let request = Message::from_vec(&buffer[..length]).unwrap();
+      let name = request.queries()[0].name().clone();
+      let mut response = Message::new();
+      response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .add_query(request.queries()[0].clone())
+        .add_answer(Record::from_rdata(name.clone(), 0, rdata));
+      socket.send_to(&response.to_vec().unwrap(), peer).unwrap();
+      name
+    });
+    (addr, handle)
+  }
+
+  #[test]
+  fn test_dns_public_ip() {
+    let (resolver, server) =
+      dns_server(RData::A(rdata::A(Ipv4Addr::new(1, 2, 3, 4))));
+    let ip = public_ip(Some(&format!("@{}", resolver)), Family::V4).unwrap();
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+    assert_eq!(
+      server.join().unwrap(),
+      Name::from_str(intra_common::OPENDNS_NAME).unwrap()
+    );
+
+    let (resolver, server) = dns_server(RData::TXT(rdata::TXT::new(vec![
+      String::from("edns0-client-subnet 1.2.3.0/24"),
+      String::from("2001:db8::1"),
+    ])));
+    let argument = format!("google@{}", resolver);
+    let ip = public_ip(Some(&argument), Family::V6).unwrap();
+    assert_eq!(
+      ip,
+      IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))
+    );
+    server.join().unwrap();
+
+    assert!(parse_argument(Some("cloudflare"), Family::V4).is_err());
+  }
+}
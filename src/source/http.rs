@@ -0,0 +1,109 @@
+//! Public address reported by "what is my IP" HTTP services.
+//!
+//! Arguments are a comma separated list of URLs answering the address as
+//! plain text, a built-in list is used without arguments. Every service is
+//! asked and the answers must agree, services failing to answer are skipped.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{parse_reply, Family};
+use crate::error::{Error, Result};
+use crate::provider::interface::CAO_USER_AGENT;
+
+mod intra_common {
+  use std::time::Duration;
+
+  pub const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+  pub const ENDPOINTS_V4: &[&str] = &[
+    "https://api.ipify.org",
+    "https://ipv4.icanhazip.com",
+    "https://v4.ident.me",
+  ];
+  pub const ENDPOINTS_V6: &[&str] = &[
+    "https://api6.ipify.org",
+    "https://ipv6.icanhazip.com",
+    "https://v6.ident.me",
+  ];
+}
+
+fn fetch(
+  client: &reqwest::blocking::Client,
+  endpoint: &str,
+  family: Family,
+) -> Result<IpAddr> {
+  let response = client.get(endpoint).send()?.error_for_status()?;
+  parse_reply(&response.text()?, family)
+}
+
+pub fn public_ip(arguments: Option<&str>, family: Family) -> Result<IpAddr> {
+  let endpoints: Vec<&str> = match arguments {
+    Some(arguments) => arguments
+      .split(',')
+      .map(str::trim)
+      .filter(|e| !e.is_empty())
+      .collect(),
+    None => match family {
+      Family::V4 => intra_common::ENDPOINTS_V4.to_vec(),
+      Family::V6 => intra_common::ENDPOINTS_V6.to_vec(),
+    },
+  };
+
+  // bind to the family so that dual stack services answer with it
+  let local = match family {
+    Family::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    Family::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+  };
+  let client = reqwest::blocking::Client::builder()
+    .local_address(local)
+    .timeout(intra_common::HTTP_TIMEOUT)
+    .user_agent(CAO_USER_AGENT)
+    .build()?;
+
+  let mut found: Option<(IpAddr, &str)> = None;
+  for endpoint in endpoints {
+    match fetch(&client, endpoint, family) {
+      Ok(ip) => {
+        tracing::debug!("HTTP SOURCE: {} {}", endpoint, ip);
+        match found {
+          Some((other, other_endpoint)) if other != ip => {
+            return Err(Error::Reason(format!(
+              "Address sources disagree: {} from {}, {} from {}",
+              other, other_endpoint, ip, endpoint
+            )))
+          }
+          Some(_) => {}
+          None => found = Some((ip, endpoint)),
+        }
+      }
+      Err(err) => tracing::warn!("Address source {} failed: {}", endpoint, err),
+    }
+  }
+
+  found
+    .map(|(ip, _)| ip)
+    .ok_or_else(|| Error::Reason(format!("No public {} address found", family)))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mock::http_server;
+
+  #[test]
+  fn test_http_public_ip() {
+    let (first, _) = http_server(vec![String::from("1.2.3.4\n")]);
+    let (second, _) = http_server(vec![String::from("1.2.3.4")]);
+    let (broken, _) = http_server(vec![String::from("<html></html>")]);
+    let endpoints = format!("{}, {}, {}", first, broken, second);
+    assert_eq!(
+      public_ip(Some(&endpoints), Family::V4).unwrap(),
+      IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))
+    );
+
+    let (first, _) = http_server(vec![String::from("1.2.3.4")]);
+    let (second, _) = http_server(vec![String::from("2.3.4.5")]);
+    let endpoints = format!("{},{}", first, second);
+    assert!(public_ip(Some(&endpoints), Family::V4).is_err());
+  }
+}
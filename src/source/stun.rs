@@ -0,0 +1,233 @@
+//! Public address reported by a STUN server (RFC 5389).
+//!
+//! The argument is the server as `host[:port]`, a public server is used
+//! without argument. A Binding Request is sent from a socket of the wanted
+//! family and the XOR-MAPPED-ADDRESS of the response is the address.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{
+  IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket,
+};
+
+use super::Family;
+use crate::error::{Error, Result};
+
+mod intra_common {
+  use std::time::Duration;
+
+  pub const STUN_SERVER: &str = "stun.l.google.com:19302";
+  pub const STUN_PORT: u16 = 3478;
+  /// Requests sent before giving up, UDP may drop some.
+  pub const STUN_ATTEMPTS: usize = 3;
+  pub const STUN_TIMEOUT: Duration = Duration::from_secs(2);
+
+  pub const MAGIC_COOKIE: u32 = 0x2112_a442;
+  pub const BINDING_REQUEST: u16 = 0x0001;
+  pub const BINDING_RESPONSE: u16 = 0x0101;
+  pub const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+  pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+  pub const FAMILY_V4: u8 = 0x01;
+  pub const FAMILY_V6: u8 = 0x02;
+}
+
+type TransactionId = [u8; 12];
+
+fn transaction_id() -> TransactionId {
+  let mut id = [0; 12];
+  let state = RandomState::new();
+  for (i, chunk) in id.chunks_mut(8).enumerate() {
+    let mut hasher = state.build_hasher();
+    hasher.write_usize(i);
+    chunk.copy_from_slice(&hasher.finish().to_be_bytes()[..chunk.len()]);
+  }
+  id
+}
+
+fn binding_request(id: &TransactionId) -> Vec<u8> {
+  let mut request = Vec::with_capacity(20);
+  request.extend_from_slice(&intra_common::BINDING_REQUEST.to_be_bytes());
+  request.extend_from_slice(&0u16.to_be_bytes());
+  request.extend_from_slice(&intra_common::MAGIC_COOKIE.to_be_bytes());
+  request.extend_from_slice(id);
+  request
+}
+
+/// Decode the value of a (XOR-)MAPPED-ADDRESS attribute.
+fn mapped_address(
+  value: &[u8],
+  id: &TransactionId,
+  xor: bool,
+) -> Option<IpAddr> {
+  let mut mask = [0; 16];
+  if xor {
+    mask[..4].copy_from_slice(&intra_common::MAGIC_COOKIE.to_be_bytes());
+    mask[4..].copy_from_slice(id);
+  }
+  match (*value.get(1)?, value.len()) {
+    (intra_common::FAMILY_V4, 8) => {
+      let mut ip = [0; 4];
+      for (i, b) in ip.iter_mut().enumerate() {
+        *b = value[4 + i] ^ mask[i];
+      }
+      Some(IpAddr::V4(Ipv4Addr::from(ip)))
+    }
+    (intra_common::FAMILY_V6, 20) => {
+      let mut ip = [0; 16];
+      for (i, b) in ip.iter_mut().enumerate() {
+        *b = value[4 + i] ^ mask[i];
+      }
+      Some(IpAddr::V6(Ipv6Addr::from(ip)))
+    }
+    _ => None,
+  }
+}
+
+/// Address of a Binding Response to the request `id`, `None` when the
+/// message is not such a response.
+fn parse_response(response: &[u8], id: &TransactionId) -> Option<IpAddr> {
+  if response.len() < 20
+    || response[0..2] != intra_common::BINDING_RESPONSE.to_be_bytes()
+    || response[4..8] != intra_common::MAGIC_COOKIE.to_be_bytes()
+    || &response[8..20] != id
+  {
+    return None;
+  }
+  let length = u16::from_be_bytes([response[2], response[3]]) as usize;
+  let attributes = response.get(20..20 + length)?;
+
+  let mut mapped = None;
+  let mut offset = 0;
+  while offset + 4 <= attributes.len() {
+    let kind = u16::from_be_bytes([attributes[offset], attributes[offset + 1]]);
+    let size =
+      u16::from_be_bytes([attributes[offset + 2], attributes[offset + 3]])
+        as usize;
+    let value = attributes.get(offset + 4..offset + 4 + size)?;
+    match kind {
+      intra_common::ATTR_XOR_MAPPED_ADDRESS => {
+        return mapped_address(value, id, true)
+      }
+      intra_common::ATTR_MAPPED_ADDRESS => {
+        mapped = mapped_address(value, id, false)
+      }
+      _ => {}
+    }
+    // attributes are padded to 4 bytes
+    offset += 4 + size.div_ceil(4) * 4;
+  }
+  mapped
+}
+
+fn server_addr(server: &str, family: Family) -> Result<SocketAddr> {
+  let addrs: Vec<SocketAddr> = match server.to_socket_addrs() {
+    Ok(addrs) => addrs.collect(),
+    Err(_) => (
+      server.trim_start_matches('[').trim_end_matches(']'),
+      intra_common::STUN_PORT,
+    )
+      .to_socket_addrs()?
+      .collect(),
+  };
+  addrs
+    .into_iter()
+    .find(|addr| family.matches(&addr.ip()))
+    .ok_or_else(|| {
+      Error::Reason(format!("No {} address of server {}", family, server))
+    })
+}
+
+pub fn public_ip(argument: Option<&str>, family: Family) -> Result<IpAddr> {
+  let server = argument
+    .map(str::trim)
+    .filter(|s| !s.is_empty())
+    .unwrap_or(intra_common::STUN_SERVER);
+  let server = server_addr(server, family)?;
+
+  let local = match family {
+    Family::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    Family::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+  };
+  let socket = UdpSocket::bind((local, 0))?;
+  socket.set_read_timeout(Some(intra_common::STUN_TIMEOUT))?;
+
+  let id = transaction_id();
+  let request = binding_request(&id);
+  let mut buffer = [0; 1024];
+  for _ in 0..intra_common::STUN_ATTEMPTS {
+    tracing::debug!("STUN: {}", server);
+    socket.send_to(&request, server)?;
+    while let Ok((length, peer)) = socket.recv_from(&mut buffer) {
+      if peer != server {
+        continue;
+      }
+      if let Some(ip) = parse_response(&buffer[..length], &id) {
+        return super::parse_reply(&ip.to_string(), family);
+      }
+    }
+  }
+  Err(Error::Reason(format!(
+    "No response from STUN server {}",
+    server
+  )))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::thread;
+
+  /// Binding Response mapping to `addr`, as a server would send it.
+  fn binding_response(id: &TransactionId, addr: IpAddr) -> Vec<u8> {
+    let mut mask = intra_common::MAGIC_COOKIE.to_be_bytes().to_vec();
+    mask.extend_from_slice(id);
+    let (family, ip) = match addr {
+      IpAddr::V4(ip) => (intra_common::FAMILY_V4, ip.octets().to_vec()),
+      IpAddr::V6(ip) => (intra_common::FAMILY_V6, ip.octets().to_vec()),
+    };
+    let mut value = vec![0, family, 0, 0];
+    value.extend(ip.iter().zip(&mask).map(|(b, m)| b ^ m));
+
+    let mut response = intra_common::BINDING_RESPONSE.to_be_bytes().to_vec();
+    // a SOFTWARE attribute needing padding comes first
+    let length = 4 + 8 + 4 + value.len() as u16;
+    response.extend_from_slice(&length.to_be_bytes());
+    response.extend_from_slice(&mask);
+    response.extend_from_slice(&[0x80, 0x22, 0, 5, b'm', b'o', b'c', b'k']);
+    response.extend_from_slice(&[b'!', 0, 0, 0]);
+    response
+      .extend_from_slice(&intra_common::ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+    response.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    response.extend_from_slice(&value);
+    response
+  }
+
+  #[test]
+  fn test_stun_response() {
+    let id = transaction_id();
+    let ip: IpAddr = "2001:db8::1234".parse().unwrap();
+    assert_eq!(parse_response(&binding_response(&id, ip), &id), Some(ip));
+    assert_eq!(parse_response(&binding_response(&id, ip), &[0; 12]), None);
+  }
+
+  #[test]
+  fn test_stun_public_ip() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+      let mut buffer = [0; 1024];
+      let (length, peer) = server.recv_from(&mut buffer).unwrap();
+      assert_eq!(length, 20);
+      let mut id = [0; 12];
+      id.copy_from_slice(&buffer[8..20]);
+      let mapped = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+      server
+        .send_to(&binding_response(&id, mapped), peer)
+        .unwrap();
+    });
+
+    let ip = public_ip(Some(&addr.to_string()), Family::V4).unwrap();
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+    handle.join().unwrap();
+  }
+}
@@ -0,0 +1,230 @@
+//! External address of the UPnP Internet Gateway Device of the LAN, for
+//! hosts behind a home router.
+//!
+//! The gateway is discovered with an SSDP search, sent to the multicast
+//! group or to `host[:port]` given as argument, then asked for its address
+//! with the `GetExternalIPAddress` action of its WAN connection service.
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use reqwest::Url;
+
+use super::{parse_reply, Family};
+use crate::error::{Error, Result};
+use crate::provider::interface::CAO_USER_AGENT;
+
+mod intra_common {
+  use std::time::Duration;
+
+  pub const SSDP_ADDR: &str = "239.255.255.250:1900";
+  pub const SSDP_PORT: u16 = 1900;
+  /// Time given to gateways to answer the search.
+  pub const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+  pub const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+  pub const IGD_DEVICE: &str =
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+  /// Services having the `GetExternalIPAddress` action.
+  pub const WAN_SERVICES: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+  ];
+}
+
+/// Text of the first `tag` element of `xml`, namespaces are not handled.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+  let open = format!("<{}", tag);
+  let mut rest = xml;
+  loop {
+    let start = rest.find(&open)? + open.len();
+    rest = &rest[start..];
+    if rest.starts_with('>') || rest.starts_with(char::is_whitespace) {
+      break;
+    }
+  }
+  let content = &rest[rest.find('>')? + 1..];
+  Some(content[..content.find("</")?].trim())
+}
+
+/// Search the gateway and return the location of its description.
+fn discover(target: SocketAddr) -> Result<String> {
+  let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+  let search = format!(
+    "M-SEARCH * HTTP/1.1\r\n\
+     HOST: {}\r\n\
+     MAN: \"ssdp:discover\"\r\n\
+     MX: 2\r\n\
+     ST: {}\r\n\r\n",
+    intra_common::SSDP_ADDR,
+    intra_common::IGD_DEVICE
+  );
+  socket.send_to(search.as_bytes(), target)?;
+
+  let deadline = Instant::now() + intra_common::SSDP_TIMEOUT;
+  let mut buffer = [0; 2048];
+  loop {
+    let remain = deadline.saturating_duration_since(Instant::now());
+    if remain.is_zero() {
+      break;
+    }
+    socket.set_read_timeout(Some(remain))?;
+    let length = match socket.recv(&mut buffer) {
+      Ok(length) => length,
+      Err(_) => break,
+    };
+    let response = String::from_utf8_lossy(&buffer[..length]);
+    tracing::debug!("SSDP: {}", response);
+    let location = response.lines().find_map(|line| {
+      let (name, value) = line.split_once(':')?;
+      if name.trim().eq_ignore_ascii_case("location") {
+        Some(String::from(value.trim()))
+      } else {
+        None
+      }
+    });
+    if let Some(location) = location {
+      return Ok(location);
+    }
+  }
+  Err(Error::Reason(String::from("No UPnP gateway found")))
+}
+
+/// Find the WAN connection service of the description, as its type and
+/// control URL.
+fn wan_service(description: &str) -> Option<(&str, &str)> {
+  description.split("<service>").skip(1).find_map(|service| {
+    let service_type = xml_text(service, "serviceType")?;
+    if intra_common::WAN_SERVICES.contains(&service_type) {
+      Some((service_type, xml_text(service, "controlURL")?))
+    } else {
+      None
+    }
+  })
+}
+
+pub fn public_ip(argument: Option<&str>, family: Family) -> Result<IpAddr> {
+  if family == Family::V6 {
+    return Err(Error::Reason(String::from(
+      "UPnP gateways only report an IPv4 address",
+    )));
+  }
+  let target = argument
+    .map(str::trim)
+    .filter(|s| !s.is_empty())
+    .unwrap_or(intra_common::SSDP_ADDR);
+  let target = match target.to_socket_addrs() {
+    Ok(mut addrs) => addrs.next(),
+    Err(_) => (target, intra_common::SSDP_PORT).to_socket_addrs()?.next(),
+  }
+  .ok_or_else(|| Error::Reason(format!("No such gateway: {}", target)))?;
+
+  let location = discover(target)?;
+  tracing::debug!("UPNP LOCATION: {}", location);
+  let client = reqwest::blocking::Client::builder()
+    .timeout(intra_common::HTTP_TIMEOUT)
+    .user_agent(CAO_USER_AGENT)
+    .build()?;
+  let description = client.get(&location).send()?.error_for_status()?.text()?;
+  let (service, control) = wan_service(&description).ok_or_else(|| {
+    Error::Reason(format!("No WAN connection service at {}", location))
+  })?;
+  let control = Url::parse(&location)
+    .and_then(|location| location.join(control))
+    .map_err(|err| Error::Reason(format!("Bad control URL: {}", err)))?;
+
+  let body = format!(
+    "<?xml version=\"1.0\"?>\
+     <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+     s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+     <s:Body><u:GetExternalIPAddress xmlns:u=\"{}\"/></s:Body>\
+     </s:Envelope>",
+    service
+  );
+  tracing::debug!("UPNP CONTROL: {} {}", control, service);
+  let response = client
+    .post(control)
+    .header("Content-Type", "text/xml; charset=\"utf-8\"")
+    .header(
+      "SOAPAction",
+      format!("\"{}#GetExternalIPAddress\"", service),
+    )
+    .body(body)
+    .send()?
+    .text()?;
+  let address =
+    xml_text(&response, "NewExternalIPAddress").ok_or_else(|| {
+      Error::Reason(format!("GetExternalIPAddress failed: {}", response))
+    })?;
+  parse_reply(address, family)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::mock::http_server;
+  use std::net::Ipv4Addr;
+  use std::thread;
+
+  const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+        <controlURL>/ctl/L3F</controlURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+        <controlURL>/ctl/IPConn</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+  const RESPONSE: &str = r#"<?xml version="1.0"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <u:GetExternalIPAddressResponse xmlns:u="urn:schemas-upnp-org:service:WANIPConnection:1">
+      <NewExternalIPAddress>1.2.3.4</NewExternalIPAddress>
+    </u:GetExternalIPAddressResponse>
+  </s:Body>
+</s:Envelope>"#;
+
+  #[test]
+  fn test_upnp_public_ip() {
+    let (url, http) =
+      http_server(vec![String::from(DESCRIPTION), String::from(RESPONSE)]);
+    let ssdp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = ssdp.local_addr().unwrap();
+    let ssdp = thread::spawn(move || {
+      let mut buffer = [0; 1024];
+      let (length, peer) = ssdp.recv_from(&mut buffer).unwrap();
+      let response = format!(
+        "HTTP/1.1 200 OK\r\nST: {}\r\nLOCATION: {}/rootDesc.xml\r\n\r\n",
+        intra_common::IGD_DEVICE,
+        url
+      );
+      ssdp.send_to(response.as_bytes(), peer).unwrap();
+      String::from_utf8_lossy(&buffer[..length]).into_owned()
+    });
+
+    let ip = public_ip(Some(&addr.to_string()), Family::V4).unwrap();
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+
+    assert!(ssdp.join().unwrap().starts_with("M-SEARCH * HTTP/1.1\r\n"));
+    let requests = http.join().unwrap();
+    assert_eq!(requests[0].path, "/rootDesc.xml");
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].path, "/ctl/IPConn");
+    assert_eq!(
+      requests[1].header("SOAPAction"),
+      Some(
+        "\"urn:schemas-upnp-org:service:WANIPConnection:1\
+         #GetExternalIPAddress\""
+      )
+    );
+  }
+}
@@ -0,0 +1,237 @@
+use crate::error::{Error, Result};
+use crate::provider::interface::{
+  list_all, DnsProvider, Record, RecordFilter, RecordSpec,
+};
+
+/// Process exit code when the record already holds the value.
+pub const EXIT_UNCHANGED: i32 = 0;
+/// Process exit code when a new record was created.
+pub const EXIT_CREATED: i32 = 10;
+/// Process exit code when an existing record was modified.
+pub const EXIT_UPDATED: i32 = 11;
+
+#[derive(Debug, PartialEq)]
+pub enum SyncResult {
+  Created(String),
+  Updated(String),
+  Unchanged(String),
+}
+
+impl SyncResult {
+  pub fn action(&self) -> &'static str {
+    match self {
+      SyncResult::Created(_) => "created",
+      SyncResult::Updated(_) => "updated",
+      SyncResult::Unchanged(_) => "unchanged",
+    }
+  }
+
+  pub fn id(&self) -> &str {
+    match self {
+      SyncResult::Created(id)
+      | SyncResult::Updated(id)
+      | SyncResult::Unchanged(id) => id,
+    }
+  }
+
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      SyncResult::Created(_) => EXIT_CREATED,
+      SyncResult::Updated(_) => EXIT_UPDATED,
+      SyncResult::Unchanged(_) => EXIT_UNCHANGED,
+    }
+  }
+}
+
+impl std::fmt::Display for SyncResult {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {}", self.action(), self.id())
+  }
+}
+
+/// Whether `record` is the one described by sub-domain, type and line, the
+/// line being given by name or by id.
+pub fn record_match(
+  record: &Record,
+  sub_domain: &str,
+  record_type: &str,
+  record_line: &str,
+) -> bool {
+  record.sub_domain == sub_domain
+    && record.r_type.eq_ignore_ascii_case(record_type)
+    && record.on_line(record_line)
+}
+
+/// Make sure exactly one record with the given sub-domain, type and line
+/// holds `value` and the fields set in `spec`, creating or modifying it
+/// only when needed.
+pub fn sync_record(
+  provider: &dyn DnsProvider,
+  sub_domain: &str,
+  record_type: &str,
+  record_line: &str,
+  value: &str,
+  spec: &RecordSpec,
+) -> Result<SyncResult> {
+  let filter = RecordFilter::sub_domain(Some(sub_domain));
+  let records = list_all(provider, &filter)?.records;
+  let matched: Vec<&Record> = records
+    .iter()
+    .filter(|r| record_match(r, sub_domain, record_type, record_line))
+    .collect();
+
+  match matched.as_slice() {
+    [] => {
+      let id = provider.add_record(
+        sub_domain,
+        record_type,
+        record_line,
+        value,
+        spec,
+      )?;
+      Ok(SyncResult::Created(id))
+    }
+    [record] if record.value == value && spec.satisfied_by(record) => {
+      Ok(SyncResult::Unchanged(record.id.clone()))
+    }
+    [record] => {
+      let id = provider.modify_record(
+        &record.id,
+        Some(sub_domain),
+        record_type,
+        record_line,
+        value,
+        spec,
+      )?;
+      Ok(SyncResult::Updated(id))
+    }
+    _ => Err(Error::Reason(format!(
+      "Ambiguous records for {} {} {}: {}",
+      sub_domain,
+      record_type,
+      record_line,
+      matched
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+    ))),
+  }
+}
+
+/// Ids of the records with the given sub-domain, type and line, the type
+/// and the line matching any record when not given.
+///
+/// Fails when no record matches, or when several do unless `all` is set.
+pub fn find_ids(
+  provider: &dyn DnsProvider,
+  sub_domain: &str,
+  record_type: Option<&str>,
+  record_line: Option<&str>,
+  all: bool,
+) -> Result<Vec<String>> {
+  if record_line == Some("") {
+    return Err(Error::Reason(String::from("Empty record line")));
+  }
+  let filter = RecordFilter {
+    sub_domain: Some(String::from(sub_domain)),
+    record_type: record_type.map(String::from),
+    record_line: record_line.map(String::from),
+    ..RecordFilter::default()
+  };
+  let ids: Vec<String> = list_all(provider, &filter)?
+    .records
+    .into_iter()
+    .map(|r| r.id)
+    .collect();
+  let selector = [Some(sub_domain), record_type, record_line]
+    .iter()
+    .flatten()
+    .copied()
+    .collect::<Vec<_>>()
+    .join(" ");
+
+  match ids.len() {
+    0 => Err(Error::Reason(format!("No record for {}", selector))),
+    1 => Ok(ids),
+    _ if all => Ok(ids),
+    _ => Err(Error::Reason(format!(
+      "Ambiguous records for {}: {}, use --all to select every one",
+      selector,
+      ids.join(", ")
+    ))),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::memory;
+
+  fn record(id: &str, sub_domain: &str, value: &str) -> Record {
+    memory::record(id, sub_domain, "A", value)
+  }
+
+  #[test]
+  fn test_sync_record() {
+    let provider = memory::Provider::with_records(vec![
+      record("1", "www", "1.2.3.4"),
+      record("2", "home", "1.2.3.4"),
+    ]);
+
+    let spec = RecordSpec::default();
+
+    let result = sync_record(&provider, "home", "A", "默认", "1.2.3.4", &spec);
+    assert_eq!(result.unwrap(), SyncResult::Unchanged(String::from("2")));
+
+    let result = sync_record(&provider, "home", "A", "默认", "2.3.4.5", &spec);
+    assert_eq!(result.unwrap(), SyncResult::Updated(String::from("2")));
+    assert_eq!(provider.records.borrow()[1].value, "2.3.4.5");
+
+    // a different TTL is an update too
+    let ttl = RecordSpec {
+      ttl: Some(60),
+      ..RecordSpec::default()
+    };
+    let result = sync_record(&provider, "home", "A", "默认", "2.3.4.5", &ttl);
+    assert_eq!(result.unwrap(), SyncResult::Updated(String::from("2")));
+    assert_eq!(provider.records.borrow()[1].ttl, Some(60));
+
+    let result = sync_record(&provider, "nas", "A", "0", "3.4.5.6", &spec);
+    assert_eq!(result.unwrap(), SyncResult::Created(String::from("3")));
+    assert_eq!(provider.records.borrow().len(), 3);
+
+    // the records of other lines are not the one of the line id
+    provider
+      .add_record("nas", "A", "电信", "4.5.6.7", &spec)
+      .unwrap();
+    let result = sync_record(&provider, "nas", "A", "0", "3.4.5.6", &spec);
+    assert_eq!(result.unwrap(), SyncResult::Unchanged(String::from("3")));
+
+    provider
+      .add_record("www", "A", "默认", "5.6.7.8", &spec)
+      .unwrap();
+    assert!(
+      sync_record(&provider, "www", "A", "默认", "1.2.3.4", &spec).is_err()
+    );
+  }
+
+  #[test]
+  fn test_find_ids() {
+    let provider = memory::Provider::with_records(vec![
+      record("1", "www", "1.2.3.4"),
+      record("2", "www", "2.3.4.5"),
+      record("3", "home", "1.2.3.4"),
+    ]);
+
+    let ids = find_ids(&provider, "home", Some("A"), Some("0"), false);
+    assert_eq!(ids.unwrap(), vec!["3"]);
+    assert!(find_ids(&provider, "home", Some("A"), Some(""), true).is_err());
+    assert!(find_ids(&provider, "home", None, Some("10=0"), true).is_err());
+    assert!(find_ids(&provider, "www", Some("A"), None, false).is_err());
+    let ids = find_ids(&provider, "www", None, None, true);
+    assert_eq!(ids.unwrap(), vec!["1", "2"]);
+    assert!(find_ids(&provider, "nas", None, None, true).is_err());
+    assert!(find_ids(&provider, "home", Some("AAAA"), None, true).is_err());
+  }
+}
@@ -0,0 +1,222 @@
+//! Address change notifications, so that the daemon reacts to a new address
+//! without waiting for the next poll.
+//!
+//! On Linux an rtnetlink socket subscribed to the address groups reports
+//! `RTM_NEWADDR` and `RTM_DELADDR`, elsewhere or when the socket can not be
+//! opened the watcher only waits and the daemon keeps polling.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+pub struct Watcher {
+  #[cfg(target_os = "linux")]
+  socket: Option<netlink::Socket>,
+}
+
+impl Watcher {
+  pub fn new() -> Self {
+    #[cfg(target_os = "linux")]
+    {
+      let socket = match netlink::Socket::open() {
+        Ok(socket) => Some(socket),
+        Err(err) => {
+          tracing::warn!("Address notifications unavailable: {}", err);
+          None
+        }
+      };
+      Watcher { socket }
+    }
+    #[cfg(not(target_os = "linux"))]
+    Watcher {}
+  }
+
+  /// Wait at most `timeout` for address changes, returning the names of
+  /// the interfaces whose addresses changed.
+  pub fn wait(&mut self, timeout: Duration) -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    if let Some(socket) = &self.socket {
+      match socket.wait(timeout) {
+        Ok(names) => return names,
+        Err(err) => {
+          tracing::warn!("Address notifications stopped: {}", err);
+          self.socket = None;
+        }
+      }
+    }
+    sleep(timeout);
+    Vec::new()
+  }
+}
+
+#[cfg(target_os = "linux")]
+mod netlink {
+  use std::io;
+  use std::mem;
+  use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+  use std::time::Duration;
+
+  const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+  const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+  const RTM_NEWADDR: u16 = 20;
+  const RTM_DELADDR: u16 = 21;
+  /// Size of `nlmsghdr`.
+  const NLMSG_HDRLEN: usize = 16;
+  /// Size of `ifaddrmsg`.
+  const IFADDRMSG_LEN: usize = 8;
+
+  pub struct Socket {
+    fd: OwnedFd,
+  }
+
+  impl Socket {
+    pub fn open() -> io::Result<Self> {
+      // SAFETY: plain system calls, the descriptor is owned once created.
+      unsafe {
+        let fd = libc::socket(
+          libc::AF_NETLINK,
+          libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+          libc::NETLINK_ROUTE,
+        );
+        if fd < 0 {
+          return Err(io::Error::last_os_error());
+        }
+        let fd = OwnedFd::from_raw_fd(fd);
+
+        let mut addr: libc::sockaddr_nl = mem::zeroed();
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR;
+        let result = libc::bind(
+          fd.as_raw_fd(),
+          &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+          mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        );
+        if result < 0 {
+          return Err(io::Error::last_os_error());
+        }
+        Ok(Socket { fd })
+      }
+    }
+
+    /// Wait for notifications and read every pending one.
+    pub fn wait(&self, timeout: Duration) -> io::Result<Vec<String>> {
+      let mut pollfd = libc::pollfd {
+        fd: self.fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+      };
+      let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+      // SAFETY: `pollfd` outlives the call.
+      let ready = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+      if ready < 0 {
+        let err = io::Error::last_os_error();
+        return match err.kind() {
+          io::ErrorKind::Interrupted => Ok(Vec::new()),
+          _ => Err(err),
+        };
+      }
+
+      if ready == 0 {
+        return Ok(Vec::new());
+      }
+
+      let mut names = Vec::new();
+      let mut buffer = [0u8; 8192];
+      loop {
+        // SAFETY: `buffer` is writable for its whole length.
+        let length = unsafe {
+          libc::recv(
+            self.fd.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            buffer.len(),
+            libc::MSG_DONTWAIT,
+          )
+        };
+        if length < 0 {
+          let err = io::Error::last_os_error();
+          match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => break,
+            // notifications were dropped, report every interface
+            _ if err.raw_os_error() == Some(libc::ENOBUFS) => {
+              names.push(String::new());
+              continue;
+            }
+            _ => return Err(err),
+          }
+        }
+        for index in parse_events(&buffer[..length as usize]) {
+          let name = interface_name(index);
+          if !names.contains(&name) {
+            names.push(name);
+          }
+        }
+      }
+      Ok(names)
+    }
+  }
+
+  fn interface_name(index: u32) -> String {
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    // SAFETY: `name` holds IF_NAMESIZE bytes as required.
+    let result = unsafe { libc::if_indextoname(index, name.as_mut_ptr()) };
+    if result.is_null() {
+      // gone with its address, report every interface
+      return String::new();
+    }
+    // SAFETY: the name is nul terminated on success.
+    unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) }
+      .to_string_lossy()
+      .into_owned()
+  }
+
+  /// Interface indexes of the address messages in `buffer`.
+  pub fn parse_events(buffer: &[u8]) -> Vec<u32> {
+    let mut indexes = Vec::new();
+    let mut offset = 0;
+    while offset + NLMSG_HDRLEN <= buffer.len() {
+      let header = &buffer[offset..];
+      let length =
+        u32::from_ne_bytes([header[0], header[1], header[2], header[3]])
+          as usize;
+      let kind = u16::from_ne_bytes([header[4], header[5]]);
+      if length < NLMSG_HDRLEN || offset + length > buffer.len() {
+        break;
+      }
+      if (kind == RTM_NEWADDR || kind == RTM_DELADDR)
+        && length >= NLMSG_HDRLEN + IFADDRMSG_LEN
+      {
+        let message = &header[NLMSG_HDRLEN..];
+        indexes.push(u32::from_ne_bytes([
+          message[4], message[5], message[6], message[7],
+        ]));
+      }
+      // messages are aligned to 4 bytes
+      offset += length.div_ceil(4) * 4;
+    }
+    indexes
+  }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+
+    fn message(kind: u16, index: u32) -> Vec<u8> {
+      let length = (NLMSG_HDRLEN + IFADDRMSG_LEN) as u32;
+      let mut message = length.to_ne_bytes().to_vec();
+      message.extend_from_slice(&kind.to_ne_bytes());
+      message.extend_from_slice(&[0; 10]);
+      message.extend_from_slice(&[10, 64, 0, 0]);
+      message.extend_from_slice(&index.to_ne_bytes());
+      message
+    }
+
+    #[test]
+    fn test_parse_events() {
+      let mut buffer = message(RTM_NEWADDR, 2);
+      // RTM_NEWLINK
+      buffer.extend(message(16, 3));
+      buffer.extend(message(RTM_DELADDR, 4));
+      assert_eq!(parse_events(&buffer), vec![2, 4]);
+      assert!(parse_events(&buffer[..20]).is_empty());
+    }
+  }
+}
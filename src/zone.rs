@@ -0,0 +1,429 @@
+//! Import and export of every record of a domain as a file.
+
+#[cfg(feature = "bind")]
+mod bind;
+mod data;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::output::{print_all, Format, Outcome};
+use crate::plan::is_zone_record;
+use crate::provider::interface::{
+  list_all, DnsProvider, Record, RecordFilter, RecordSpec,
+};
+
+pub const ZONE_FORMATS: &[&str] = &["bind", "json", "yaml", "csv"];
+
+/// Format of the files, the master file of BIND or a list of records with
+/// the same fields for every provider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoneFormat {
+  Bind,
+  Json,
+  Yaml,
+  Csv,
+}
+
+impl FromStr for ZoneFormat {
+  type Err = String;
+
+  fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+    match format {
+      "bind" => Ok(ZoneFormat::Bind),
+      "json" => Ok(ZoneFormat::Json),
+      "yaml" => Ok(ZoneFormat::Yaml),
+      "csv" => Ok(ZoneFormat::Csv),
+      _ => Err(format!(
+        "Unknown zone format {}, expected one of {}",
+        format,
+        ZONE_FORMATS.join(", ")
+      )),
+    }
+  }
+}
+
+impl ZoneFormat {
+  /// Format given by the extension of `file`, bind for any other.
+  fn of_file(file: &str) -> Self {
+    let extension = Path::new(file)
+      .extension()
+      .and_then(|e| e.to_str())
+      .map(str::to_lowercase);
+    match extension.as_deref() {
+      Some("json") => ZoneFormat::Json,
+      Some("yaml" | "yml") => ZoneFormat::Yaml,
+      Some("csv") => ZoneFormat::Csv,
+      _ => ZoneFormat::Bind,
+    }
+  }
+}
+
+/// A record read from a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+  pub sub_domain: String,
+  pub record_type: String,
+  pub value: String,
+  /// Line of the record, master files having none.
+  pub record_line: Option<String>,
+  pub spec: RecordSpec,
+}
+
+#[cfg(feature = "bind")]
+fn render_bind(zone: &str, records: &[Record]) -> Result<String> {
+  Ok(bind::render(zone, records))
+}
+
+#[cfg(not(feature = "bind"))]
+fn render_bind(_zone: &str, _records: &[Record]) -> Result<String> {
+  Err(Error::Reason(String::from(
+    "Zone files of BIND need the bind feature",
+  )))
+}
+
+#[cfg(feature = "bind")]
+fn parse_bind(zone: &str, content: &str) -> Result<Vec<Entry>> {
+  bind::parse(zone, content)
+}
+
+#[cfg(not(feature = "bind"))]
+fn parse_bind(_zone: &str, _content: &str) -> Result<Vec<Entry>> {
+  Err(Error::Reason(String::from(
+    "Zone files of BIND need the bind feature",
+  )))
+}
+
+/// Write the records as a file of `format`.
+pub fn render(
+  zone: &str,
+  records: &[Record],
+  format: ZoneFormat,
+) -> Result<String> {
+  match format {
+    ZoneFormat::Bind => render_bind(zone, records),
+    ZoneFormat::Json => data::render_json(records),
+    ZoneFormat::Yaml => data::render_yaml(records),
+    ZoneFormat::Csv => data::render_csv(records),
+  }
+}
+
+/// Records of a file of `format`.
+pub fn parse(
+  zone: &str,
+  content: &str,
+  format: ZoneFormat,
+) -> Result<Vec<Entry>> {
+  match format {
+    ZoneFormat::Bind => parse_bind(zone, content),
+    ZoneFormat::Json => data::parse_json(content),
+    ZoneFormat::Yaml => data::parse_yaml(content),
+    ZoneFormat::Csv => data::parse_csv(content),
+  }
+}
+
+/// Whether the line is the default line of its provider.
+pub fn is_default_line(record_line: &str) -> bool {
+  matches!(record_line, "" | "0" | "默认" | "default")
+}
+
+/// Name of the zone of a domain as given to the provider, the RFC 2136
+/// provider taking `ZONE@SERVER`.
+pub fn zone_name(domain: &str) -> &str {
+  domain.split('@').next().unwrap_or(domain)
+}
+
+/// Every record with its line and type named as on DNSPod, so that the
+/// file can be imported to another provider. Lines and types without such
+/// a name keep the one of the provider.
+fn exported(provider: &dyn DnsProvider) -> Result<Vec<Record>> {
+  let records = list_all(provider, &RecordFilter::default())?.records;
+  Ok(
+    records
+      .into_iter()
+      .map(|record| {
+        let r_line = provider.common_line(&record.r_line);
+        let r_type = provider.common_type(&record.r_type);
+        Record {
+          r_line: r_line.unwrap_or(record.r_line),
+          r_type: r_type.unwrap_or(record.r_type),
+          ..record
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Print every record as a file of `format`.
+pub fn export(
+  provider: &dyn DnsProvider,
+  domain: &str,
+  format: ZoneFormat,
+) -> Result<()> {
+  let records = exported(provider)?;
+  print!("{}", render(zone_name(domain), &records, format)?);
+  Ok(())
+}
+
+/// Add the records of `file`, of `format` or else the one of its
+/// extension.
+///
+/// Records are added on `record_line` when given, else on their own line
+/// in the file, else on the default line. Lines and types of the file are
+/// named as on DNSPod, the records without counterpart on the provider are
+/// reported and skipped. SOA records and NS records of the apex belong to
+/// the zone of the provider, they are skipped too. Failures are reported
+/// and the remaining records are still added.
+pub fn import(
+  provider: &dyn DnsProvider,
+  domain: &str,
+  file: &str,
+  format: Option<ZoneFormat>,
+  record_line: Option<&str>,
+  output: Format,
+) -> Result<()> {
+  let content = std::fs::read_to_string(file)?;
+  let format = format.unwrap_or_else(|| ZoneFormat::of_file(file));
+  let entries = parse(zone_name(domain), &content, format)?;
+
+  let mut failed = 0;
+  let mut outcomes = Vec::new();
+  for entry in entries {
+    if is_zone_record(&entry.sub_domain, &entry.record_type)
+      || entry.record_type.eq_ignore_ascii_case("SOA")
+    {
+      eprintln!(
+        "{} {} {}: skipped, managed by the provider",
+        entry.sub_domain, entry.record_type, entry.value
+      );
+      continue;
+    }
+    let record_type = match provider.provider_type(&entry.record_type) {
+      Some(record_type) => record_type,
+      None => {
+        eprintln!(
+          "{} {} {}: skipped, no type {} on the provider",
+          entry.sub_domain, entry.record_type, entry.value, entry.record_type
+        );
+        continue;
+      }
+    };
+    let record_line = match (record_line, entry.record_line.as_deref()) {
+      (Some(record_line), _) => String::from(record_line),
+      (None, None | Some("0")) => String::from("0"),
+      (None, Some(line)) => match provider.provider_line(line) {
+        Some(record_line) => record_line,
+        None => {
+          eprintln!(
+            "{} {} {}: skipped, no line {} on the provider",
+            entry.sub_domain, entry.record_type, entry.value, line
+          );
+          continue;
+        }
+      },
+    };
+    let result = provider.add_record(
+      &entry.sub_domain,
+      &record_type,
+      &record_line,
+      &entry.value,
+      &entry.spec,
+    );
+    match result {
+      Ok(id) => outcomes.push(Outcome::changed(
+        "added",
+        id,
+        Some(&entry.sub_domain),
+        &record_type,
+        &record_line,
+        &entry.value,
+      )),
+      Err(err) => {
+        failed += 1;
+        eprintln!(
+          "{} {} {}: {}",
+          entry.sub_domain, entry.record_type, entry.value, err
+        );
+      }
+    }
+  }
+
+  print_all(output, &outcomes)?;
+  if failed == 0 {
+    Ok(())
+  } else {
+    Err(Error::Reason(format!("{} record(s) failed", failed)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::provider::interface::RecordStatus;
+  use crate::provider::memory;
+
+  fn record(sub_domain: &str, r_type: &str, value: &str) -> Record {
+    memory::record("1", sub_domain, r_type, value)
+  }
+
+  #[cfg(feature = "bind")]
+  #[test]
+  fn test_bind_render() {
+    let mut mx = record("@", "MX", "mail.example.com");
+    mx.mx = Some(10);
+    let mut disabled = record("old", "A", "1.2.3.4");
+    disabled.status = Some(RecordStatus::Disabled);
+    let mut telecom = record("www", "A", "2.3.4.5");
+    telecom.r_line = String::from("电信");
+    telecom.ttl = Some(60);
+    let records = vec![
+      record("www", "A", "1.2.3.4"),
+      telecom,
+      mx,
+      record("@", "TXT", "v=spf1 -all"),
+      record("blog", "CNAME", "example.github.io."),
+      disabled,
+    ];
+
+    let rendered = bind::render("example.com", &records);
+    assert_eq!(
+      rendered,
+      "$ORIGIN example.com.\n\
+       $TTL 600\n\
+       www 600 IN A 1.2.3.4\n\
+       www 60 IN A 2.3.4.5 ; line 电信\n\
+       @ 600 IN MX 10 mail.example.com.\n\
+       @ 600 IN TXT \"v=spf1 -all\"\n\
+       blog 600 IN CNAME example.github.io.\n\
+       ; disabled: old 600 IN A 1.2.3.4\n"
+    );
+    // the disabled record is left out
+    assert_eq!(bind::parse("example.com", &rendered).unwrap().len(), 5);
+  }
+
+  #[cfg(feature = "bind")]
+  #[test]
+  fn test_bind_parse() {
+    let content = "$ORIGIN example.com.\n\
+                   $TTL 3600\n\
+                   @ IN SOA ns1.example.net. admin.example.com. \
+                   ( 1 7200 900 1209600 300 )\n\
+                   @ IN NS ns1.example.net.\n\
+                   www IN A 1.2.3.4 ; comment\n\
+                   \x20   IN TXT \"hello world\"\n\
+                   @ 600 IN MX 10 mail\n";
+    let entries = bind::parse("example.com", content).unwrap();
+    let entry = |sub_domain: &str, r_type: &str| {
+      entries
+        .iter()
+        .find(|e| e.sub_domain == sub_domain && e.record_type == r_type)
+        .unwrap()
+        .clone()
+    };
+
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entry("@", "NS").value, "ns1.example.net.");
+    let mx = entry("@", "MX");
+    assert_eq!(mx.value, "mail.example.com.");
+    assert_eq!(mx.spec.mx, Some(10));
+    assert_eq!(mx.spec.ttl, Some(600));
+    assert_eq!(entry("www", "A").spec.ttl, Some(3600));
+    assert_eq!(entry("www", "TXT").value, "hello world");
+  }
+
+  #[test]
+  fn test_data_round_trip() {
+    let mut mx = record("@", "MX", "mail.example.com");
+    mx.mx = Some(10);
+    mx.line_id = Some(String::from("0"));
+    mx.remark = Some(String::from("mail, \"primary\""));
+    let mut disabled = record("old", "TXT", "a\nb");
+    disabled.status = Some(RecordStatus::Disabled);
+    disabled.weight = Some(5);
+    let records = vec![record("www", "A", "1.2.3.4"), mx, disabled];
+
+    for format in [ZoneFormat::Json, ZoneFormat::Yaml, ZoneFormat::Csv] {
+      let rendered = render("example.com", &records, format).unwrap();
+      let entries = parse("example.com", &rendered, format).unwrap();
+      assert_eq!(entries.len(), 3, "{:?}", format);
+      for (entry, record) in entries.iter().zip(&records) {
+        assert_eq!(entry.sub_domain, record.sub_domain);
+        assert_eq!(entry.record_type, record.r_type);
+        assert_eq!(entry.value, record.value);
+        assert_eq!(entry.record_line.as_deref(), Some("默认"));
+        assert_eq!(entry.spec.ttl, record.ttl);
+        assert_eq!(entry.spec.mx, record.mx);
+        assert_eq!(entry.spec.weight, record.weight);
+        assert_eq!(entry.spec.status, record.status);
+        assert_eq!(entry.spec.remark, record.remark);
+      }
+    }
+
+    let csv = render("example.com", &records, ZoneFormat::Csv).unwrap();
+    assert!(csv.starts_with(
+      "id,sub_domain,type,value,line,line_id,ttl,mx,weight,status,remark\n"
+    ));
+    // fields left out of a file are unset
+    let entries = parse(
+      "example.com",
+      "sub_domain,type,value\nwww,A,1.2.3.4\n",
+      ZoneFormat::Csv,
+    )
+    .unwrap();
+    assert_eq!(entries[0].record_line, None);
+    assert_eq!(entries[0].spec, RecordSpec::default());
+  }
+
+  #[test]
+  fn test_import_to_other_lines() {
+    let source = memory::Provider::without_lines(vec![Record {
+      r_line: String::from("default"),
+      ..record("www", "a", "1.2.3.4")
+    }]);
+    let records = exported(&source).unwrap();
+    assert_eq!(
+      (records[0].r_type.as_str(), records[0].r_line.as_str()),
+      ("A", "默认")
+    );
+    let file = std::env::temp_dir()
+      .join(format!("cao-zone-test-{}.json", std::process::id()));
+    let rendered = render("example.com", &records, ZoneFormat::Json).unwrap();
+    std::fs::write(&file, rendered).unwrap();
+
+    let target = memory::Provider::default();
+    let file_name = file.to_str().unwrap();
+    import(&target, "example.com", file_name, None, None, Format::Plain)
+      .unwrap();
+    let records = target.records.borrow();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].r_line, "默认");
+    assert_eq!(records[0].value, "1.2.3.4");
+    drop(records);
+
+    // lines of DNSPod but the default one have no counterpart
+    let target = memory::Provider::without_lines(vec![]);
+    let rendered = render(
+      "example.com",
+      &[Record {
+        r_line: String::from("电信"),
+        ..record("www", "A", "1.2.3.4")
+      }],
+      ZoneFormat::Json,
+    )
+    .unwrap();
+    std::fs::write(&file, rendered).unwrap();
+    import(&target, "example.com", file_name, None, None, Format::Plain)
+      .unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert!(target.records.borrow().is_empty());
+  }
+
+  #[test]
+  fn test_zone_format_of_file() {
+    assert_eq!(ZoneFormat::of_file("zone.JSON"), ZoneFormat::Json);
+    assert_eq!(ZoneFormat::of_file("a/zone.yml"), ZoneFormat::Yaml);
+    assert_eq!(ZoneFormat::of_file("zone.csv"), ZoneFormat::Csv);
+    assert_eq!(ZoneFormat::of_file("example.com.zone"), ZoneFormat::Bind);
+  }
+}
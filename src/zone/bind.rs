@@ -0,0 +1,126 @@
+//! RFC 1035 master files, as read and written by BIND.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use trust_dns_proto::rr::{self, Name, RData};
+use trust_dns_proto::serialize::txt::{Lexer, Parser};
+
+use super::{is_default_line, Entry};
+use crate::error::Result;
+use crate::provider::interface::{Record, RecordSpec, RecordStatus};
+
+/// TTL of the records without one when none is the most common.
+const DEFAULT_TTL: u32 = 600;
+
+/// Name as written in the data of the record, absolute when it has no
+/// trailing dot yet as providers give names of the data without it.
+fn absolute(name: &str) -> String {
+  if name.ends_with('.') {
+    String::from(name)
+  } else {
+    format!("{}.", name)
+  }
+}
+
+/// Data of the record in the master file format.
+fn rdata(record: &Record) -> String {
+  let value = record.value.as_str();
+  match record.r_type.to_uppercase().as_str() {
+    // the priority is a field of its own for most providers
+    "MX" if !value.contains(' ') => {
+      format!("{} {}", record.mx.unwrap_or(0), absolute(value))
+    }
+    "CNAME" | "NS" | "PTR" | "DNAME" => absolute(value),
+    "SRV" => match value.rsplit_once(' ') {
+      Some((fields, target)) => format!("{} {}", fields, absolute(target)),
+      None => String::from(value),
+    },
+    "TXT" | "SPF" if !value.starts_with('"') => {
+      format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    _ => String::from(value),
+  }
+}
+
+/// Write the records as a master file of `zone`.
+///
+/// Lines other than the default one are noted in a comment, disabled
+/// records are commented out.
+pub fn render(zone: &str, records: &[Record]) -> String {
+  let mut ttls = BTreeMap::new();
+  for ttl in records.iter().filter_map(|r| r.ttl) {
+    *ttls.entry(ttl).or_insert(0) += 1;
+  }
+  let default_ttl = ttls
+    .iter()
+    .max_by_key(|(_, count)| **count)
+    .map_or(DEFAULT_TTL, |(ttl, _)| *ttl);
+
+  let mut file = format!("$ORIGIN {}\n$TTL {}\n", absolute(zone), default_ttl);
+  for record in records {
+    // explicit, as some parsers take the last stated TTL over $TTL
+    let mut line = format!(
+      "{} {} IN {} {}",
+      record.sub_domain,
+      record.ttl.unwrap_or(default_ttl),
+      record.r_type,
+      rdata(record)
+    );
+    if record.status == Some(RecordStatus::Disabled) {
+      line = format!("; disabled: {}", line);
+    }
+    if !is_default_line(&record.r_line) {
+      line = format!("{} ; line {}", line, record.r_line);
+    }
+    file.push_str(&line);
+    file.push('\n');
+  }
+  file
+}
+
+/// Sub-domain of `name` in `zone`, `@` for the apex.
+fn sub_domain(name: &Name, zone: &Name) -> String {
+  let name = name.to_lowercase().to_ascii();
+  let zone = zone.to_lowercase().to_ascii();
+  match name.strip_suffix(&zone) {
+    Some("") => String::from("@"),
+    Some(sub) => String::from(sub.trim_end_matches('.')),
+    None => name,
+  }
+}
+
+fn entry(record: &rr::Record, zone: &Name) -> Option<Entry> {
+  let mut spec = RecordSpec {
+    ttl: Some(record.ttl()),
+    ..RecordSpec::default()
+  };
+  let value = match record.data()? {
+    RData::MX(mx) => {
+      spec.mx = Some(mx.preference());
+      mx.exchange().to_string()
+    }
+    rdata => rdata.to_string(),
+  };
+  Some(Entry {
+    sub_domain: sub_domain(record.name(), zone),
+    record_type: record.record_type().to_string(),
+    value,
+    record_line: None,
+    spec,
+  })
+}
+
+/// Records of a master file of `zone`.
+pub fn parse(zone: &str, content: &str) -> Result<Vec<Entry>> {
+  let origin = Name::from_str(&absolute(zone))?;
+  let (origin, records) =
+    Parser::new().parse(Lexer::new(content), Some(origin))?;
+  Ok(
+    records
+      .values()
+      .flat_map(|set| set.records_without_rrsigs())
+      .filter_map(|record| entry(record, &origin))
+      .collect(),
+  )
+}
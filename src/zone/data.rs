@@ -0,0 +1,114 @@
+//! Records as JSON, YAML or CSV, with the same fields for every provider.
+
+use serde::{Deserialize, Serialize};
+
+use super::Entry;
+use crate::error::{Error, Result};
+use crate::provider::interface::{Record, RecordSpec, RecordStatus};
+
+/// A record in the files, every field being written even when unset so
+/// that the columns of CSV are always the same.
+///
+/// The id is the one of the exporting provider, it is not used by import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneRecord {
+  #[serde(default)]
+  pub id: Option<String>,
+  pub sub_domain: String,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  pub value: String,
+  #[serde(default)]
+  pub line: Option<String>,
+  #[serde(default)]
+  pub line_id: Option<String>,
+  #[serde(default)]
+  pub ttl: Option<u32>,
+  #[serde(default)]
+  pub mx: Option<u16>,
+  #[serde(default)]
+  pub weight: Option<u32>,
+  #[serde(default)]
+  pub status: Option<RecordStatus>,
+  #[serde(default)]
+  pub remark: Option<String>,
+}
+
+impl From<&Record> for ZoneRecord {
+  fn from(record: &Record) -> Self {
+    ZoneRecord {
+      id: Some(record.id.clone()),
+      sub_domain: record.sub_domain.clone(),
+      record_type: record.r_type.clone(),
+      value: record.value.clone(),
+      line: Some(record.r_line.clone()).filter(|l| !l.is_empty()),
+      line_id: record.line_id.clone(),
+      ttl: record.ttl,
+      mx: record.mx,
+      weight: record.weight,
+      status: record.status,
+      remark: record.remark.clone(),
+    }
+  }
+}
+
+impl From<ZoneRecord> for Entry {
+  fn from(record: ZoneRecord) -> Self {
+    Entry {
+      sub_domain: record.sub_domain,
+      record_type: record.record_type,
+      value: record.value,
+      record_line: record.line.or(record.line_id),
+      spec: RecordSpec {
+        ttl: record.ttl,
+        mx: record.mx,
+        weight: record.weight,
+        status: record.status,
+        remark: record.remark,
+      },
+    }
+  }
+}
+
+fn zone_records(records: &[Record]) -> Vec<ZoneRecord> {
+  records.iter().map(ZoneRecord::from).collect()
+}
+
+pub fn render_json(records: &[Record]) -> Result<String> {
+  Ok(format!(
+    "{}\n",
+    serde_json::to_string_pretty(&zone_records(records))?
+  ))
+}
+
+pub fn render_yaml(records: &[Record]) -> Result<String> {
+  Ok(serde_yaml::to_string(&zone_records(records))?)
+}
+
+pub fn render_csv(records: &[Record]) -> Result<String> {
+  let mut writer = csv::Writer::from_writer(Vec::new());
+  for record in zone_records(records) {
+    writer.serialize(record)?;
+  }
+  let csv = writer
+    .into_inner()
+    .map_err(|err| Error::Reason(err.to_string()))?;
+  Ok(String::from_utf8(csv)?)
+}
+
+pub fn parse_json(content: &str) -> Result<Vec<Entry>> {
+  let records: Vec<ZoneRecord> = serde_json::from_str(content)?;
+  Ok(records.into_iter().map(Entry::from).collect())
+}
+
+pub fn parse_yaml(content: &str) -> Result<Vec<Entry>> {
+  let records: Vec<ZoneRecord> = serde_yaml::from_str(content)?;
+  Ok(records.into_iter().map(Entry::from).collect())
+}
+
+pub fn parse_csv(content: &str) -> Result<Vec<Entry>> {
+  csv::Reader::from_reader(content.as_bytes())
+    .deserialize()
+    .map(|record: csv::Result<ZoneRecord>| Ok(Entry::from(record?)))
+    .collect()
+}